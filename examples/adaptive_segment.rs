@@ -5,7 +5,7 @@ use liniya::{
     scene::SceneBuilder,
     shape::{BoxOutline, Sphere},
 };
-use nalgebra::{Point2, Point3, Vector3};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
 
 fn main() {
     let unit_size = Vector3::new(0.5, 0.5, 0.5);
@@ -17,8 +17,10 @@ fn main() {
         &Point3::new(2.0, 2.0, 0.0),
         2.0,
         Some(std::f64::consts::PI / 10.0),
-        None,
-    );
+        Some(std::f64::consts::PI / 8.0),
+    )
+    .pole_gap(std::f64::consts::PI / 20.0)
+    .orientation(&UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.41));
 
     let scene = SceneBuilder::new()
         .add(b)
@@ -43,9 +45,9 @@ fn main() {
             .set("width", w)
             .set("height", h)
             .add(svg::node::element::Style::new(
-                "polyline { fill: none; stroke-width: 1px; }",
+                "polyline { fill: none; }",
             ));
     doc = doc.add(scene.render_to_svg(&camera, (w, h)));
 
-    println!("{}", doc.to_string())
+    println!("{}", doc)
 }
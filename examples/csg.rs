@@ -0,0 +1,54 @@
+extern crate liniya;
+
+use liniya::{
+    camera::Camera,
+    scene::{SceneBuilder, Style},
+    shape::{BoxOutline, Cylinder, Difference, Sphere},
+};
+use nalgebra::{Point3, Vector3};
+
+fn main() {
+    // a sphere with one octant carved out
+    let sphere = Difference::new(
+        Sphere::new(&Point3::new(1.0, 0.0, 0.0), 1.5, Some(0.2), Some(0.2)),
+        BoxOutline::from_extents(Point3::new(1.0, 0.0, 0.0), Point3::new(3.0, 2.0, 2.0)),
+    );
+
+    // a plate drilled through the middle
+    let plate = Difference::new(
+        BoxOutline::from_extents(Point3::new(-3.0, -2.0, -0.5), Point3::new(-1.0, 1.0, 0.5)),
+        Cylinder::from_endpoints(
+            &Point3::new(-2.0, -0.5, -0.5),
+            &Point3::new(-2.0, -0.5, 0.5),
+            0.5,
+        )
+        .ring_spacing(Some(0.1)),
+    );
+
+    let scene = SceneBuilder::new()
+        .add(sphere)
+        .add_styled(plate, Style::new().color("steelblue").pen(2))
+        .build();
+
+    let w = 800.0;
+    let h = 600.0;
+    let camera = Camera::new()
+        .look_at(
+            &Point3::new(2.0, 3.0, 7.0),
+            &Point3::new(-0.5, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        )
+        .perspective(std::f64::consts::FRAC_PI_3, w / h, 1.0, 30.0)
+        .set_resolution(0.004);
+
+    let mut doc =
+        svg::Document::new()
+            .set("width", w)
+            .set("height", h)
+            .add(svg::node::element::Style::new(
+                "polyline { fill: none; }",
+            ));
+    doc = doc.add(scene.render_to_svg(&camera, (w, h)));
+
+    println!("{}", doc);
+}
@@ -0,0 +1,61 @@
+extern crate liniya;
+
+use liniya::{
+    camera::Camera,
+    plot::{GcodeWriter, Origin, PathMerge, PathOrder, PenControl, SegmentDedupe},
+    scene::SceneBuilder,
+    shape::{BoxOutline, Sphere},
+};
+use nalgebra::{Point2, Point3, Vector3};
+
+fn main() {
+    let mut scene_builder = SceneBuilder::new();
+    for i in -3..=3 {
+        for j in -3..=3 {
+            let c = Point3::new(i as f64, 0.0, j as f64);
+            scene_builder =
+                scene_builder.add(BoxOutline::from_extents(c, c + Vector3::new(1.0, 0.5, 1.0)));
+        }
+    }
+    let scene = scene_builder
+        .add(Sphere::new(
+            &Point3::new(0.5, 1.5, 0.5),
+            1.0,
+            Some(0.3),
+            None,
+        ))
+        .build();
+
+    // A landscape A4 sheet.
+    let (w, h) = (297.0, 210.0);
+    let camera = Camera::new()
+        .look_at(
+            &Point3::new(6.0, 6.0, 8.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        )
+        .perspective(std::f64::consts::FRAC_PI_3, w / h, 1.0, 30.0);
+
+    // Draw each shared edge once, in as few strokes as possible, one
+    // pen at a time with little travel between them, starting from the
+    // top left corner.
+    let paths = SegmentDedupe::new(1e-6).apply(scene.render(&camera));
+    let paths = PathMerge::new(1e-6).apply(paths);
+    let (paths, travel) = PathOrder::new().start(Point2::new(-1.0, 1.0)).apply(paths);
+    eprintln!(
+        "{} paths, pen-up travel reduced by {:.0}%",
+        paths.len(),
+        100.0 * travel.saved() / travel.before
+    );
+
+    // A GRBL plotter with a servo pen lift, homed at the top left.
+    let writer = GcodeWriter::new(w, h)
+        .pen(PenControl::Servo {
+            up: "M5".to_string(),
+            down: "M3 S30".to_string(),
+            dwell: 0.2,
+        })
+        .origin(Origin::TopLeft)
+        .draw_feed(2000.0);
+    print!("{}", writer.to_gcode(&paths));
+}
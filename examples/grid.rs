@@ -1,6 +1,11 @@
 extern crate liniya;
 
-use liniya::{camera::Camera, scene::SceneBuilder, shape::BoxOutline};
+use liniya::{
+    camera::Camera,
+    plot::{PathMerge, SegmentDedupe},
+    scene::{scene::format_svg_paths, SceneBuilder},
+    shape::BoxOutline,
+};
 use nalgebra::{Point3, Vector3};
 
 fn main() {
@@ -37,9 +42,13 @@ fn main() {
             .set("width", w)
             .set("height", h)
             .add(svg::node::element::Style::new(
-                "polyline { fill: none; stroke-width: 1px; }",
+                "polyline { fill: none; }",
             ));
-    doc = doc.add(scene.render_to_svg(&camera, (w, h)));
+    // Draw the edges shared by neighboring boxes once, joined into
+    // longer lines.
+    let paths = SegmentDedupe::new(1e-6).apply(scene.render(&camera));
+    let paths = PathMerge::new(1e-6).apply(paths);
+    doc = doc.add(format_svg_paths(&paths, w, h));
 
-    println!("{}", doc.to_string())
+    println!("{}", doc)
 }
@@ -0,0 +1,69 @@
+extern crate liniya;
+
+use liniya::{
+    camera::Camera,
+    scene::{Group, SceneBuilder},
+    shape::BoxOutline,
+};
+use nalgebra::{Isometry3, Point3, Vector3};
+use std::sync::Arc;
+
+/// Return a simple chair made of boxes, standing on the origin.
+fn chair() -> Group {
+    let mut chair = Group::new()
+        .add(BoxOutline::from_extents(
+            Point3::new(-0.5, 0.9, -0.5),
+            Point3::new(0.5, 1.0, 0.5),
+        ))
+        .add(BoxOutline::from_extents(
+            Point3::new(-0.5, 1.0, -0.5),
+            Point3::new(0.5, 2.0, -0.4),
+        ));
+    for (x, z) in &[(-0.45, -0.45), (0.45, -0.45), (-0.45, 0.45), (0.45, 0.45)] {
+        chair = chair.add(BoxOutline::new(
+            Point3::new(*x, 0.45, *z),
+            Vector3::new(0.05, 0.45, 0.05),
+        ));
+    }
+    chair
+}
+
+fn main() {
+    // Build the chair once, and place it around a circle.
+    let chair = Arc::new(chair());
+    let mut scene_builder = SceneBuilder::new();
+    const N_CHAIRS: usize = 8;
+    for i in 0..N_CHAIRS {
+        let angle = std::f64::consts::TAU * i as f64 / N_CHAIRS as f64;
+        let placement =
+            Isometry3::rotation(Vector3::y() * angle) * Isometry3::translation(0.0, 0.0, -3.0);
+        scene_builder = scene_builder.add_group(
+            Group::new()
+                .transform(&placement)
+                .add_instance(chair.clone()),
+        );
+    }
+    let scene = scene_builder.build();
+
+    let w = 800.0;
+    let h = 600.0;
+    let camera = Camera::new()
+        .look_at(
+            &Point3::new(4.0, 6.0, 8.0),
+            &Point3::new(0.0, 0.5, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        )
+        .perspective(std::f64::consts::FRAC_PI_3, w / h, 1.0, 30.0)
+        .set_resolution(0.004);
+
+    let mut doc =
+        svg::Document::new()
+            .set("width", w)
+            .set("height", h)
+            .add(svg::node::element::Style::new(
+                "polyline { fill: none; }",
+            ));
+    doc = doc.add(scene.render_to_svg(&camera, (w, h)));
+
+    println!("{}", doc);
+}
@@ -0,0 +1,70 @@
+extern crate liniya;
+
+use liniya::{
+    camera::Camera,
+    scene::SceneBuilder,
+    shape::{EdgeMode, Mesh},
+    Shape,
+};
+use nalgebra::Vector3;
+
+/// Render a Wavefront OBJ or STL file given on the command line,
+/// framed to fit its bounding box.
+///
+/// Pass `--silhouette` or `--crease` (or both) to draw outlines or
+/// sharp features instead of the full wireframe.
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    const CREASE_ANGLE: f64 = std::f64::consts::PI / 6.0;
+    let silhouette = args.iter().any(|a| a == "--silhouette");
+    let crease = args.iter().any(|a| a == "--crease");
+    let edge_mode = match (silhouette, crease) {
+        (false, false) => EdgeMode::Wireframe,
+        (true, false) => EdgeMode::Silhouette,
+        (false, true) => EdgeMode::Crease(CREASE_ANGLE),
+        (true, true) => EdgeMode::Outline(CREASE_ANGLE),
+    };
+    let path = args.iter().find(|a| !a.starts_with("--")).ok_or_else(|| {
+        anyhow::anyhow!("usage: mesh [--silhouette] [--crease] <file.obj|file.stl>")
+    })?;
+    let mesh = if path.to_lowercase().ends_with(".stl") {
+        Mesh::load_stl(path)?
+    } else {
+        Mesh::load_obj(path)?
+    }
+    .edge_mode(edge_mode);
+
+    let bb = mesh.bounding_box();
+    let target = bb.center();
+    let radius = bb.half_extents().norm();
+
+    let w = 800.0;
+    let h = 600.0;
+    let camera = Camera::new()
+        .look_at(
+            &(target + Vector3::new(1.0, 0.8, 1.5).normalize() * radius * 2.5),
+            &target,
+            &Vector3::new(0.0, 1.0, 0.0),
+        )
+        .perspective(
+            std::f64::consts::FRAC_PI_3,
+            w / h,
+            radius * 0.1,
+            radius * 10.0,
+        )
+        .set_resolution(0.002);
+
+    let scene = SceneBuilder::new().add(mesh).build();
+
+    let mut doc =
+        svg::Document::new()
+            .set("width", w)
+            .set("height", h)
+            .add(svg::node::element::Style::new(
+                "polyline { fill: none; }",
+            ));
+    doc = doc.add(scene.render_to_svg(&camera, (w, h)));
+
+    println!("{}", doc);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+extern crate liniya;
+
+use liniya::{
+    camera::Camera,
+    plot::PathSimplify,
+    scene::{scene::format_svg_paths, SceneBuilder},
+    shape::{FunctionSurface, SurfaceStrokes},
+};
+use nalgebra::{Point2, Point3, Vector3};
+
+fn main() {
+    let surface = FunctionSurface::new(
+        |x, z| {
+            let r = (x * x + z * z).sqrt();
+            (r * 2.0).cos() * (-r * 0.3).exp()
+        },
+        Point2::new(-5.0, -5.0),
+        Point2::new(5.0, 5.0),
+    )
+    .strokes(SurfaceStrokes::AlongX)
+    .spacing(0.2)
+    .step(0.1);
+
+    let scene = SceneBuilder::new().add(surface).build();
+
+    let w = 800.0;
+    let h = 600.0;
+    let camera = Camera::new()
+        .look_at(
+            &Point3::new(6.0, 5.0, 9.0),
+            &Point3::new(0.0, -0.5, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        )
+        .perspective(std::f64::consts::FRAC_PI_3, w / h, 1.0, 30.0)
+        .set_resolution(0.004);
+
+    let mut doc =
+        svg::Document::new()
+            .set("width", w)
+            .set("height", h)
+            .add(svg::node::element::Style::new(
+                "polyline { fill: none; }",
+            ));
+    // Drop points closer than a tenth of a pixel to the lines between
+    // their neighbors.
+    let paths = PathSimplify::new(0.2 / w).apply(scene.render(&camera));
+    doc = doc.add(format_svg_paths(&paths, w, h));
+
+    println!("{}", doc);
+}
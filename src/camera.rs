@@ -1,10 +1,29 @@
 //! Perspective or orthographic cameras for rendering scenes
 use super::common::*;
+use crate::error::RenderError;
 use crate::frustum::{ClipResult, ClipResultPartial, Frustum};
-use crate::shape::Path;
+use crate::shape::{assert_spacing, Path};
 use crate::util::box_plane_intersection;
 use itertools::Itertools;
 
+/// How the visibility of points along a path is decided during
+/// rendering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VisibilityMode {
+    /// Cast an occlusion ray every `resolution` along each segment,
+    /// in NDC.
+    Sampled,
+
+    /// Cast an occlusion ray every `step` along each segment, and
+    /// bisect any change in visibility down to `tolerance`, both in
+    /// NDC.
+    ///
+    /// Line endings are placed to within `tolerance`, with far fewer
+    /// rays than sampling at that resolution. Occluders narrower than
+    /// `step` may still be missed.
+    Exact { step: f64, tolerance: f64 },
+}
+
 /// `Camera` determines the view and projection of a scene during
 /// rendering.
 #[derive(Clone, Debug)]
@@ -20,6 +39,9 @@ pub struct Camera {
     /// maximum screen space distance for line segments to be
     /// rendered.
     resolution: f64,
+
+    /// how visibility along segments is decided
+    visibility: VisibilityMode,
 }
 
 impl Camera {
@@ -32,6 +54,7 @@ impl Camera {
             frustum: Frustum::from_clip_matrix(proj.matrix()),
             projection: proj,
             resolution: 0.001,
+            visibility: VisibilityMode::Sampled,
         }
     }
     /// Update the internal frustum.
@@ -87,26 +110,67 @@ impl Camera {
         self.resolution
     }
 
+    /// Return a modified version of the camera, deciding visibility
+    /// with `mode`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `step` or `tolerance` of an `Exact` mode isn't
+    /// positive and finite.
+    pub fn visibility(self, mode: VisibilityMode) -> Camera {
+        if let VisibilityMode::Exact { step, tolerance } = mode {
+            assert_spacing(step, "visibility step");
+            assert_spacing(tolerance, "visibility tolerance");
+        }
+        Camera {
+            visibility: mode,
+            ..self
+        }
+    }
+
+    /// Return the visibility mode of the camera.
+    pub fn visibility_mode(&self) -> VisibilityMode {
+        self.visibility
+    }
+
     /// Return the frustum of the camera
     pub fn frustum(&self) -> &Frustum<f64> {
         &self.frustum
     }
 
+    /// Return the camera as seen from a local space, mapped into world
+    /// space by the affine `transform`.
+    ///
+    /// Projecting a local point with the new camera gives the same
+    /// result as projecting the transformed point with this one.
+    pub(crate) fn in_local_space(&self, transform: &Matrix4<f64>) -> Camera {
+        let clip_matrix = self.projection.matrix() * self.view_iso.to_matrix() * transform;
+        Camera {
+            view_iso: na::Isometry3::identity(),
+            projection: na::Projective3::from_matrix_unchecked(clip_matrix),
+            frustum: Frustum::from_clip_matrix(&clip_matrix),
+            resolution: self.resolution,
+            visibility: self.visibility,
+        }
+    }
+
     /// Clip a path into separate paths within the cameras view.
-    pub(crate) fn clip_path(&self, path: &Path) -> Vec<Path> {
+    pub(crate) fn clip_path(&self, path: &Path) -> Result<Vec<Path>, RenderError> {
         if path.len() < 2 {
-            return vec![];
+            return Ok(vec![]);
         }
 
         let mut clipped_paths: Vec<Path> = vec![];
         let mut current_path: Path = vec![];
         for i in 1..path.len() {
             let (p0, p1) = (path[i - 1], path[i]);
-            let clip_res = self.frustum.clip_line(&p0, &p1);
+            let clip_res = self.frustum.clip_line(&p0, &p1)?;
             match clip_res {
                 ClipResult::Outside => {
                     // Necessarily, there is no current path.
-                    assert!(current_path.is_empty());
+                    if !current_path.is_empty() {
+                        return Err(RenderError::DisconnectedClip);
+                    }
 
                     // No need to do anything else.
                 }
@@ -125,13 +189,17 @@ impl Camera {
                         // clip is strictly internal to the segment.
                         // Don't bother creating an intermediate current_path.
                         ClipResultPartial::Infix => {
-                            assert!(current_path.is_empty());
+                            if !current_path.is_empty() {
+                                return Err(RenderError::DisconnectedClip);
+                            }
                             clipped_paths.push(vec![c0, c1]);
                         }
                         // We're starting a path.
                         //
                         ClipResultPartial::Suffix => {
-                            assert!(current_path.is_empty());
+                            if !current_path.is_empty() {
+                                return Err(RenderError::DisconnectedClip);
+                            }
                             current_path.push(c0);
                             current_path.push(c1);
                         }
@@ -152,11 +220,13 @@ impl Camera {
         // If we have a path still in construction, it must be
         // finished, and we can add it to our clipped paths
         if !current_path.is_empty() {
-            assert!(current_path.len() > 1);
+            if current_path.len() < 2 {
+                return Err(RenderError::DisconnectedClip);
+            }
             clipped_paths.push(current_path);
         }
 
-        clipped_paths
+        Ok(clipped_paths)
     }
 
     /// Return true if the point is contained within the camera's frustum.
@@ -197,7 +267,7 @@ impl Camera {
     /// Unproject a point from NDC to world coordinates.
     pub fn unproject(&self, ndc_point: &Point3<f64>) -> Point3<f64> {
         self.view_iso
-            .inverse_transform_point(&self.projection.inverse_transform_point(&ndc_point))
+            .inverse_transform_point(&self.projection.inverse_transform_point(ndc_point))
     }
 
     /// Project a point into device coordinates, including the 3d coordinate.
@@ -209,6 +279,18 @@ impl Camera {
         self.projection.transform_point(&camera_point)
     }
 
+    /// Return the unit direction pointing back towards the camera
+    /// along the line of sight through a world point.
+    ///
+    /// For perspective cameras this varies across the scene; for
+    /// orthographic cameras it is the same everywhere.
+    pub fn view_direction(&self, world_point: &Point3<f64>) -> Vector3<f64> {
+        let p = self.project_3d(world_point);
+        let near = self.unproject(&Point3::new(p.x, p.y, -1.0));
+        let far = self.unproject(&Point3::new(p.x, p.y, 1.0));
+        (near - far).normalize()
+    }
+
     /// Return the area of a 3d polygon in projected onto 2d
     /// coordinates, in NDC.
     pub fn projected_area(&self, points: &[Point3<f64>]) -> f64 {
@@ -217,6 +299,12 @@ impl Camera {
     }
 }
 
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,4 +323,13 @@ mod tests {
         assert!(cam.is_point_visible(&Point3::new(0.0, 0.0, 4.0)));
         assert!(!cam.is_point_visible(&Point3::new(0.0, 0.0, 6.0)));
     }
+
+    #[test]
+    #[should_panic(expected = "visibility step must be positive")]
+    fn zero_visibility_step() {
+        Camera::new().visibility(VisibilityMode::Exact {
+            step: 0.0,
+            tolerance: 1e-6,
+        });
+    }
 }
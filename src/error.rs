@@ -0,0 +1,86 @@
+//! Error types returned by liniya.
+use thiserror::Error;
+
+/// Error loading geometry from a file or stream.
+#[derive(Error, Debug)]
+pub enum LoadError {
+    /// The underlying file or stream could not be read.
+    #[error("could not read input: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Text input was malformed at the given (one-based) line.
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    /// Binary input was shorter than its header claims.
+    #[error("expected {expected} bytes of binary data, found {found}")]
+    Truncated { expected: usize, found: usize },
+
+    /// The input is in a format (or variant of one) that isn't
+    /// supported.
+    #[error("unsupported format: {0}")]
+    Unsupported(String),
+
+    /// An image could not be decoded.
+    #[error("could not decode image: {0}")]
+    Image(String),
+}
+
+impl LoadError {
+    /// Shorthand for creating a `LoadError::Parse`.
+    pub(crate) fn parse(line: usize, message: impl Into<String>) -> LoadError {
+        LoadError::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Error rendering a scene, from geometry too degenerate to clip or
+/// split.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RenderError {
+    /// A segment with one end inside the camera frustum was not found
+    /// to cross any of its planes, as when it is nearly parallel to
+    /// them.
+    #[error("segment leaving the frustum does not cross its boundary")]
+    MissingFrustumCrossing,
+
+    /// A segment with both ends outside the camera frustum touched
+    /// its boundary at more than two distinct points, which a straight
+    /// segment can't.
+    #[error("segment touches the frustum boundary at {0} points")]
+    TooManyFrustumCrossings(usize),
+
+    /// Clipping a path against the camera frustum gave segments that
+    /// do not join up.
+    #[error("clipped segments of a path are not connected")]
+    DisconnectedClip,
+
+    /// Points split from a segment for rendering did not lie on a
+    /// line once unprojected, by the given deviation in direction.
+    #[error("split segment is not straight (deviation {0:e})")]
+    CurvedSplit(f64),
+}
+
+/// Output of rendering a scene with some of its paths left out, as
+/// they were too degenerate to render.
+#[derive(Error, Debug)]
+#[error("{} path(s) could not be rendered", .errors.len())]
+pub struct PartialRender<T> {
+    /// everything rendered from the remaining paths
+    pub output: T,
+
+    /// index of the shape of each path left out, and why
+    pub errors: Vec<(usize, RenderError)>,
+}
+
+impl<T> PartialRender<T> {
+    /// Return the same errors with the output converted by `f`.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> PartialRender<U> {
+        PartialRender {
+            output: f(self.output),
+            errors: self.errors,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::RenderError;
 use na::{Point3, Vector4};
 use nalgebra as na;
 use std::cmp::Ordering;
@@ -106,24 +107,25 @@ impl<F: na::RealField> Frustum<F> {
     }
 
     /// Clip a 3d line segment against the frustum.
-    pub fn clip_line(&self, p0: &na::Point3<F>, p1: &na::Point3<F>) -> ClipResult<F> {
+    ///
+    /// Fails if the segment's crossings of the frustum planes can't
+    /// be made sense of, as when it is nearly parallel to them.
+    pub fn clip_line(
+        &self,
+        p0: &na::Point3<F>,
+        p1: &na::Point3<F>,
+    ) -> Result<ClipResult<F>, RenderError> {
         let in0 = self.is_point_in(p0);
         let in1 = self.is_point_in(p1);
 
         // frustums are convex, so if both points are inside the frustum, we're done.
         if in0 && in1 {
-            return ClipResult::Inside(*p0, *p1);
+            return Ok(ClipResult::Inside(*p0, *p1));
         }
         // Get the intersections for the line segment across all planes.
         let mut isects: Vec<F> = (0..6)
             .filter_map(|i| plane_segment_directed_intersection(&self.planes[i], p0, p1))
-            .filter_map(|t| {
-                if t >= F::zero() && t <= F::one() {
-                    Some(t)
-                } else {
-                    None
-                }
-            })
+            .filter(|t| *t >= F::zero() && *t <= F::one())
             .collect();
 
         let cmp = |a: &F, b: &F| {
@@ -140,40 +142,49 @@ impl<F: na::RealField> Frustum<F> {
         // If the first point is inside, we can find the first
         // intersection and use that as the other clip point.
         if in0 {
-            let min_t: F = isects[0];
-            return ClipResult::Partial(ClipResultPartial::Prefix, *p0, p0 + (p1 - p0) * min_t);
+            let min_t: F = *isects.first().ok_or(RenderError::MissingFrustumCrossing)?;
+            return Ok(ClipResult::Partial(
+                ClipResultPartial::Prefix,
+                *p0,
+                p0 + (p1 - p0) * min_t,
+            ));
         }
 
         // If the second point inside, we can find the last
         // intersection and use that as the other clip point.
         if in1 {
-            let max_t: F = *isects.last().unwrap();
-            return ClipResult::Partial(ClipResultPartial::Suffix, p0 + (p1 - p0) * max_t, *p1);
+            let max_t: F = *isects.last().ok_or(RenderError::MissingFrustumCrossing)?;
+            return Ok(ClipResult::Partial(
+                ClipResultPartial::Suffix,
+                p0 + (p1 - p0) * max_t,
+                *p1,
+            ));
         }
 
         // If both points are outside, then we need to find the
-        // intersection points that are on the frustum.
+        // intersection points that are on the frustum. Where the
+        // segment passes through an edge or corner, it crosses several
+        // planes at once, so crossings at the same point are merged.
         let eps = na::convert(1e-5);
-        let points_on_frustum: Vec<_> = isects
-            .iter()
-            .filter_map(|t| {
-                let p = p0 + (p1 - p0) * *t;
-                if self.is_point_in_or_on(&p, eps) {
-                    Some(p)
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let mut points_on_frustum: Vec<na::Point3<F>> = vec![];
+        let mut last_t: Option<F> = None;
+        for t in isects {
+            let p = p0 + (p1 - p0) * t;
+            if !self.is_point_in_or_on(&p, eps) || last_t.is_some_and(|last| t - last <= eps) {
+                continue;
+            }
+            points_on_frustum.push(p);
+            last_t = Some(t);
+        }
 
         match points_on_frustum.len() {
-            2 => ClipResult::Partial(
+            2 => Ok(ClipResult::Partial(
                 ClipResultPartial::Infix,
                 points_on_frustum[0],
                 points_on_frustum[1],
-            ),
-            0 | 1 => ClipResult::Outside,
-            _ => panic!("Too many on-frustum intersections"),
+            )),
+            0 | 1 => Ok(ClipResult::Outside),
+            n => Err(RenderError::TooManyFrustumCrossings(n)),
         }
     }
 
@@ -182,3 +193,53 @@ impl<F: na::RealField> Frustum<F> {
         self.planes[idx as usize]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grazing_segment() {
+        let frustum = Frustum::from_clip_matrix(&na::Matrix4::<f64>::identity());
+
+        // Leaves through the right plane, too close to parallel to it
+        // to find the crossing.
+        let p0 = Point3::new(1.0 - 1e-9, 0.0, 0.0);
+        let p1 = Point3::new(1.0 + 1e-7, 0.5, 0.0);
+        assert_eq!(
+            frustum.clip_line(&p0, &p1).unwrap_err(),
+            RenderError::MissingFrustumCrossing
+        );
+
+        let p1 = Point3::new(2.0, 0.5, 0.0);
+        assert!(matches!(
+            frustum.clip_line(&p0, &p1),
+            Ok(ClipResult::Partial(ClipResultPartial::Prefix, _, _))
+        ));
+    }
+
+    #[test]
+    fn edge_crossings() {
+        let frustum = Frustum::from_clip_matrix(&na::Matrix4::<f64>::identity());
+
+        // Enters and leaves through opposite edges, crossing two
+        // planes at each.
+        let p0 = Point3::new(-2.0, -2.0, 0.0);
+        let p1 = Point3::new(2.0, 2.0, 0.0);
+        match frustum.clip_line(&p0, &p1).unwrap() {
+            ClipResult::Partial(ClipResultPartial::Infix, c0, c1) => {
+                assert!((c0 - Point3::new(-1.0, -1.0, 0.0)).norm() < 1e-9);
+                assert!((c1 - Point3::new(1.0, 1.0, 0.0)).norm() < 1e-9);
+            }
+            _ => panic!("expected the segment to be clipped at both ends"),
+        }
+
+        // Only touches an edge.
+        let p0 = Point3::new(-3.0, 1.0, 0.0);
+        let p1 = Point3::new(1.0, -3.0, 0.0);
+        assert!(matches!(
+            frustum.clip_line(&p0, &p1),
+            Ok(ClipResult::Outside)
+        ));
+    }
+}
@@ -1,10 +1,12 @@
 pub mod camera;
 pub mod common;
+pub mod error;
 pub mod frustum;
+pub mod plot;
 pub mod scene;
 pub mod shape;
 pub mod util;
 
-pub use camera::Camera;
+pub use camera::{Camera, VisibilityMode};
 pub use scene::{Scene, SceneBuilder};
 pub use shape::Shape;
@@ -0,0 +1,401 @@
+//! Remove segments that are drawn more than once.
+use super::compatible;
+use crate::common::*;
+use crate::scene::RenderedPath;
+use crate::shape::assert_spacing;
+use std::collections::HashMap;
+use std::f64::consts::{PI, SQRT_2};
+
+/// Removal of collinear, overlapping segments from rendered paths, so
+/// that edges shared by shapes are only drawn once.
+///
+/// Paths are processed in order, and each segment keeps only the
+/// parts not already drawn by an earlier one, leaving the union of
+/// each overlapping set. Paths are split where parts are removed. As
+/// with `PathMerge`, only paths drawn with the same style, or the same
+/// pen if `pen_only` is set, are compared.
+///
+/// Segments overlap where one lies within `tolerance` of the other
+/// along its whole length. Segments that only come that close part of
+/// the way along both, crossing at a shallow angle, may be left as
+/// they are, as may segments a few tolerances long lying across the
+/// middle of another.
+#[derive(Clone, Debug)]
+pub struct SegmentDedupe {
+    /// maximum distance between segments considered to overlap
+    tolerance: f64,
+
+    /// whether paths with different styles on the same pen are
+    /// compared
+    pen_only: bool,
+}
+
+/// Width of the buckets of line directions, in radians.
+const ANGLE_CELL: f64 = 1e-3;
+
+/// Length, in tolerances, below which segments are also compared with
+/// the kept segments ending near them, rather than only with those in
+/// nearby directions.
+const SHORT_SEGMENT: f64 = 8.0;
+
+/// Segment already kept, from the path with index `path`.
+struct Segment {
+    a: Point2<f64>,
+    b: Point2<f64>,
+    path: usize,
+}
+
+/// Kept segments, bucketed by direction and by the offset from the
+/// origin of a line in that direction through their midpoint.
+///
+/// Segments within `tolerance` of each other along the whole of the
+/// shorter one differ in direction by up to `2 * tolerance / length`,
+/// so each segment goes in every direction bucket that close to its
+/// own, and is found from any bucket shared with another. Below
+/// `SHORT_SEGMENT` tolerances that would be a large part of a turn, so
+/// the buckets stop there, and short segments are instead also
+/// compared with the segments ending in cells around them.
+struct LineGrid {
+    tolerance: f64,
+    offset_cell: f64,
+    end_cell: f64,
+    segments: Vec<Segment>,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+    ends: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl LineGrid {
+    fn new(tolerance: f64) -> LineGrid {
+        // Over its direction buckets, the points of a segment lie
+        // within `tolerance` plus its length (at most 2 sqrt(2) in
+        // NDC) times the bucket width of the line through its
+        // midpoint. Two such lines through overlapping segments then
+        // differ in offset by at most three tolerances and twice that
+        // slack, and neighboring offset buckets cover them.
+        LineGrid {
+            tolerance,
+            offset_cell: 3.0 * tolerance + 4.0 * SQRT_2 * ANGLE_CELL,
+            end_cell: SHORT_SEGMENT * tolerance,
+            segments: vec![],
+            buckets: HashMap::new(),
+            ends: HashMap::new(),
+        }
+    }
+
+    /// Return the cell of the grid of segment ends containing `p`.
+    fn end_key(&self, p: &Point2<f64>) -> (i64, i64) {
+        (
+            (p.x / self.end_cell).floor() as i64,
+            (p.y / self.end_cell).floor() as i64,
+        )
+    }
+
+    /// Return the direction buckets near that of the segment from `a`
+    /// to `b`, each with the offset of the line in that direction
+    /// through its midpoint.
+    fn lines(&self, a: &Point2<f64>, b: &Point2<f64>) -> impl Iterator<Item = (i64, f64)> {
+        let d = b - a;
+        let mut theta = d.y.atan2(d.x);
+        if theta < 0.0 {
+            theta += PI;
+        }
+        if theta >= PI {
+            theta -= PI;
+        }
+        let n = (PI / ANGLE_CELL).ceil() as i64;
+        let length = d.norm().max(SHORT_SEGMENT * self.tolerance);
+        let spread = (2.0 * self.tolerance / length).asin();
+        let window = ((spread / ANGLE_CELL).ceil() as i64).max(1);
+        let k = (theta / ANGLE_CELL).floor() as i64;
+        let mid = na::center(a, b);
+        (k - window..=k + window).map(move |k| {
+            // Directions wrap around at a half turn, which flips the
+            // normal, and so the sign of the offset.
+            let k = k.rem_euclid(n);
+            let theta = (k as f64 + 0.5) * ANGLE_CELL;
+            let normal = Vector2::new(-theta.sin(), theta.cos());
+            (k, normal.dot(&mid.coords))
+        })
+    }
+
+    fn insert(&mut self, segment: Segment) {
+        let index = self.segments.len();
+        let keys: Vec<_> = self
+            .lines(&segment.a, &segment.b)
+            .map(|(k, offset)| (k, (offset / self.offset_cell).floor() as i64))
+            .collect();
+        for key in keys {
+            self.buckets.entry(key).or_default().push(index);
+        }
+        let (start, end) = (self.end_key(&segment.a), self.end_key(&segment.b));
+        self.ends.entry(start).or_default().push(index);
+        if end != start {
+            self.ends.entry(end).or_default().push(index);
+        }
+        self.segments.push(segment);
+    }
+
+    /// Return the kept segments on lines near the segment from `a` to
+    /// `b`, and if it is short, those ending near it.
+    fn near(&self, a: &Point2<f64>, b: &Point2<f64>) -> Vec<&Segment> {
+        let mut found: Vec<usize> = self
+            .lines(a, b)
+            .flat_map(|(k, offset)| {
+                let r = (offset / self.offset_cell).floor() as i64;
+                (-1..=1).map(move |dr| (k, r + dr))
+            })
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .copied()
+            .collect();
+        if (b - a).norm() < self.end_cell {
+            // Ends within `tolerance` of the segment lie within
+            // `end_cell` of its midpoint.
+            let (x, y) = self.end_key(&na::center(a, b));
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if let Some(ends) = self.ends.get(&(x + dx, y + dy)) {
+                        found.extend(ends);
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found.into_iter().map(|i| &self.segments[i]).collect()
+    }
+}
+
+impl SegmentDedupe {
+    /// Create a deduplication treating segments within `tolerance` of
+    /// each other, in NDC, as the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` isn't positive and finite.
+    pub fn new(tolerance: f64) -> SegmentDedupe {
+        assert_spacing(tolerance, "dedupe tolerance");
+        SegmentDedupe {
+            tolerance,
+            pen_only: false,
+        }
+    }
+
+    /// Return a modified version of the deduplication, comparing paths
+    /// with the same pen regardless of the rest of their style if
+    /// `pen_only` is true.
+    pub fn pen_only(self, pen_only: bool) -> SegmentDedupe {
+        SegmentDedupe { pen_only, ..self }
+    }
+
+    /// Remove the parts of segments already drawn by earlier ones,
+    /// returning the remaining paths in order.
+    pub fn apply(&self, paths: Vec<RenderedPath>) -> Vec<RenderedPath> {
+        let mut grid = LineGrid::new(self.tolerance);
+        let mut deduped = vec![];
+
+        for (index, path) in paths.iter().enumerate() {
+            let mut current: Vec<Point2<f64>> = vec![];
+            for w in path.points.windows(2) {
+                let (a, b) = (w[0], w[1]);
+                let pieces = self.remaining(&paths, &grid, index, &a, &b);
+
+                // Untouched ends stay exactly where they were.
+                let at = |s: f64| match s {
+                    s if s <= 0.0 => a,
+                    s if s >= 1.0 => b,
+                    s => a + (b - a) * s,
+                };
+                for &(lo, hi) in &pieces {
+                    let (p, q) = (at(lo), at(hi));
+                    // Continue the path only from the end of the last
+                    // segment.
+                    if lo > 0.0 || current.last() != Some(&a) {
+                        if current.len() > 1 {
+                            deduped.push(RenderedPath {
+                                shape: path.shape.clone(),
+                                points: current,
+                            });
+                        }
+                        current = vec![p];
+                    }
+                    current.push(q);
+                    grid.insert(Segment {
+                        a: p,
+                        b: q,
+                        path: index,
+                    });
+                }
+            }
+            if current.len() > 1 {
+                deduped.push(RenderedPath {
+                    shape: path.shape.clone(),
+                    points: current,
+                });
+            }
+        }
+        deduped
+    }
+
+    /// Return the parts of the segment from `a` to `b` not covered by
+    /// a kept segment, as ascending intervals of its parameter.
+    fn remaining(
+        &self,
+        paths: &[RenderedPath],
+        grid: &LineGrid,
+        index: usize,
+        a: &Point2<f64>,
+        b: &Point2<f64>,
+    ) -> Vec<(f64, f64)> {
+        let d = b - a;
+        let length = d.norm();
+        if length <= self.tolerance {
+            return vec![];
+        }
+
+        let mut pieces = vec![(0.0, 1.0)];
+        for other in grid.near(a, b) {
+            if !compatible(&paths[index], &paths[other.path], self.pen_only) {
+                continue;
+            }
+            let (lo, hi) = match self.overlap(a, b, other) {
+                Some(overlap) => overlap,
+                None => continue,
+            };
+            pieces = pieces
+                .into_iter()
+                .flat_map(|(x, y)| {
+                    if hi <= x || lo >= y {
+                        vec![(x, y)]
+                    } else {
+                        [(x, lo), (hi, y)]
+                            .iter()
+                            .copied()
+                            .filter(|(x, y)| x < y)
+                            .collect()
+                    }
+                })
+                .collect();
+        }
+        pieces.retain(|(x, y)| (y - x) * length > self.tolerance);
+        pieces
+    }
+
+    /// Return the interval of the segment from `a` to `b` that lies on
+    /// `other`, if they are collinear.
+    fn overlap(&self, a: &Point2<f64>, b: &Point2<f64>, other: &Segment) -> Option<(f64, f64)> {
+        let d = b - a;
+        let param = |p: &Point2<f64>| (p - a).dot(&d) / d.norm_squared();
+        let (s0, s1) = (param(&other.a), param(&other.b));
+        let (lo, hi) = (s0.min(s1).max(0.0), s0.max(s1).min(1.0));
+        if hi <= lo {
+            return None;
+        }
+
+        let od = other.b - other.a;
+        let normal = Vector2::new(-od.y, od.x).normalize();
+        let distance = |s: f64| (a + d * s - other.a).dot(&normal).abs();
+        if distance(lo) <= self.tolerance && distance(hi) <= self.tolerance {
+            Some((lo, hi))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::test_path;
+
+    #[test]
+    fn removes_shared_edges() {
+        let length = |paths: &[RenderedPath]| {
+            paths
+                .iter()
+                .flat_map(|p| p.points.windows(2).map(|w| (w[1] - w[0]).norm()))
+                .sum::<f64>()
+        };
+
+        // Two unit squares side by side, the second shifted up by a
+        // half, sharing half of an edge drawn in opposite directions.
+        let paths = vec![
+            test_path(
+                1,
+                &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)],
+            ),
+            test_path(
+                1,
+                &[(1.0, 0.5), (2.0, 0.5), (2.0, 1.5), (1.0, 1.5), (1.0, 0.5)],
+            ),
+            test_path(1, &[(0.5, 1e-8), (1.5, 1e-8)]),
+        ];
+
+        let deduped = SegmentDedupe::new(1e-6).apply(paths.clone());
+        assert_eq!(deduped[0].points, paths[0].points);
+        assert!((length(&deduped) - (length(&paths) - 0.5 - 0.5)).abs() < 1e-6);
+
+        // The second square loses the shared part of its last edge,
+        // and the line along the bottom keeps what sticks out.
+        assert_eq!(deduped.len(), 3);
+        assert_eq!(deduped[1].points.len(), 5);
+        assert!((deduped[1].points[4].y - 1.0).abs() < 1e-9);
+        assert!((deduped[2].points[0].x - 1.0).abs() < 1e-9);
+
+        // Two short, nearly collinear segments crossing near the
+        // corner of NDC, whose directions fall in neighboring buckets
+        // and whose offsets from the origin differ by more than twice
+        // the bucket width.
+        let c = Point2::new(0.98967, 0.99033);
+        let segment = |theta: f64| {
+            let d = Vector2::new(theta.cos(), theta.sin()) * 2e-4;
+            test_path(1, &[((c - d).x, (c - d).y), ((c + d).x, (c + d).y)])
+        };
+        let deduped = SegmentDedupe::new(1e-6).apply(vec![segment(0.78405), segment(0.78595)]);
+        assert_eq!(deduped.len(), 1);
+    }
+
+    #[test]
+    fn removes_short_segments_at_an_angle() {
+        // A short segment lying within the tolerance of a long one,
+        // but turned from it by many direction buckets.
+        let long = test_path(1, &[(0.0, 0.0), (0.5, 0.0)]);
+        let short = test_path(1, &[(0.1, 0.0), (0.11, 5e-4)]);
+        let dedupe = SegmentDedupe::new(1e-3);
+
+        let deduped = dedupe.apply(vec![long.clone(), short.clone()]);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].points, long.points);
+
+        // Drawn first, the short segment splits the long one.
+        let deduped = dedupe.apply(vec![short, long]);
+        assert_eq!(deduped.len(), 3);
+        assert!((deduped[1].points[1].x - 0.1).abs() < 1e-3);
+        assert!((deduped[2].points[0].x - 0.11).abs() < 1e-3);
+    }
+
+    #[test]
+    fn removes_short_segments_near_ends() {
+        // A segment five tolerances long, turned from a long one by
+        // more than the direction buckets searched, but lying within
+        // the tolerance of it near its end.
+        let long = test_path(1, &[(0.0, 0.0), (0.5, 0.0)]);
+        let short = test_path(1, &[(0.494612, -0.000739), (0.499388, 0.000739)]);
+        let dedupe = SegmentDedupe::new(1e-3);
+
+        let deduped = dedupe.apply(vec![long.clone(), short.clone()]);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].points, long.points);
+
+        // The direction buckets of short segments stay few.
+        let grid = LineGrid::new(1e-3);
+        let (a, b) = (short.points[0], short.points[1]);
+        assert!(grid.lines(&a, &b).count() < 600);
+    }
+
+    #[test]
+    #[should_panic(expected = "dedupe tolerance must be positive")]
+    fn zero_dedupe_tolerance() {
+        SegmentDedupe::new(0.0);
+    }
+}
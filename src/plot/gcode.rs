@@ -0,0 +1,359 @@
+//! G-code output of rendered paths, for pen plotters and CNC machines.
+use super::group_by_pen;
+use crate::camera::Camera;
+use crate::common::*;
+use crate::error::PartialRender;
+use crate::scene::{RenderedPath, Scene};
+use std::io::{self, Write};
+
+/// Commands that raise and lower the pen.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PenControl {
+    /// Move the Z axis to `up` or `down`, in millimeters.
+    Z { up: f64, down: f64 },
+
+    /// Send the given commands to a servo, such as `M3 S30` and `M5`,
+    /// and wait `dwell` seconds for it to move.
+    Servo {
+        up: String,
+        down: String,
+        dwell: f64,
+    },
+}
+
+impl PenControl {
+    /// Return servo pen control, sending `up` and `down`, with no
+    /// dwell.
+    pub fn servo<U: Into<String>, D: Into<String>>(up: U, down: D) -> PenControl {
+        PenControl::Servo {
+            up: up.into(),
+            down: down.into(),
+            dwell: 0.0,
+        }
+    }
+}
+
+/// Corner of the paper at the machine origin.
+///
+/// Coordinates increase away from the corner along both edges of the
+/// paper.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+/// Units the machine works in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Units {
+    /// Millimeters, selected with `G21`.
+    Millimeters,
+
+    /// Inches, selected with `G20`.
+    Inches,
+
+    /// Whatever units the machine is already set to, given as the
+    /// number of them per millimeter. No units command is sent.
+    Unset(f64),
+}
+
+impl Units {
+    /// Return the number of units per millimeter.
+    fn per_mm(self) -> f64 {
+        match self {
+            Units::Millimeters => 1.0,
+            Units::Inches => 1.0 / 25.4,
+            Units::Unset(per_mm) => per_mm,
+        }
+    }
+}
+
+/// Writer of rendered paths as G-code, scaled onto a sheet of paper.
+///
+/// As with `format_svg_paths`, paths in NDC are stretched over the
+/// whole sheet. A pause (`M0`) is added wherever the pen number
+/// changes, to swap pens.
+#[derive(Clone, Debug)]
+pub struct GcodeWriter {
+    /// paper width and height, in millimeters
+    paper: (f64, f64),
+
+    pen: PenControl,
+
+    /// feed rate while drawing, in millimeters per minute
+    draw_feed: f64,
+
+    /// feed rate while traveling with the pen up, or `None` to travel
+    /// as fast as possible with `G0`
+    travel_feed: Option<f64>,
+
+    origin: Origin,
+
+    units: Units,
+
+    /// whether `G4` dwell times are given in milliseconds, rather than
+    /// seconds
+    dwell_milliseconds: bool,
+}
+
+impl GcodeWriter {
+    /// Create a writer for paper of `width` by `height` millimeters,
+    /// with the origin at the bottom left, raising the pen to Z 5 and
+    /// lowering it to Z 0, and drawing at 1000 mm/min.
+    ///
+    /// The G-code is written in millimeters, with dwell times in
+    /// seconds, as GRBL expects.
+    pub fn new(width: f64, height: f64) -> GcodeWriter {
+        GcodeWriter {
+            paper: (width, height),
+            pen: PenControl::Z { up: 5.0, down: 0.0 },
+            draw_feed: 1000.0,
+            travel_feed: None,
+            origin: Origin::BottomLeft,
+            units: Units::Millimeters,
+            dwell_milliseconds: false,
+        }
+    }
+
+    /// Return a modified version of the writer, raising and lowering
+    /// the pen with `pen`.
+    pub fn pen(self, pen: PenControl) -> GcodeWriter {
+        GcodeWriter { pen, ..self }
+    }
+
+    /// Return a modified version of the writer, drawing at
+    /// `draw_feed` mm/min.
+    pub fn draw_feed(self, draw_feed: f64) -> GcodeWriter {
+        GcodeWriter { draw_feed, ..self }
+    }
+
+    /// Return a modified version of the writer, traveling between
+    /// paths at `travel_feed` mm/min rather than as fast as possible.
+    pub fn travel_feed(self, travel_feed: f64) -> GcodeWriter {
+        GcodeWriter {
+            travel_feed: Some(travel_feed),
+            ..self
+        }
+    }
+
+    /// Return a modified version of the writer, with the machine
+    /// origin at the `origin` corner of the paper.
+    pub fn origin(self, origin: Origin) -> GcodeWriter {
+        GcodeWriter { origin, ..self }
+    }
+
+    /// Return a modified version of the writer, in `units`.
+    ///
+    /// Coordinates, Z heights and feed rates are all scaled from
+    /// millimeters.
+    pub fn units(self, units: Units) -> GcodeWriter {
+        GcodeWriter { units, ..self }
+    }
+
+    /// Return a modified version of the writer, giving dwell times in
+    /// milliseconds if `dwell_milliseconds` is true.
+    ///
+    /// GRBL reads the `P` parameter of `G4` as seconds, while Marlin
+    /// reads it as milliseconds.
+    pub fn dwell_milliseconds(self, dwell_milliseconds: bool) -> GcodeWriter {
+        GcodeWriter {
+            dwell_milliseconds,
+            ..self
+        }
+    }
+
+    /// Return the machine coordinates of a point in NDC.
+    fn machine_point(&self, p: &Point2<f64>) -> Point2<f64> {
+        let (w, h) = self.paper;
+        let (left, bottom) = ((p.x + 1.0) * w / 2.0, (p.y + 1.0) * h / 2.0);
+        let (x, y) = match self.origin {
+            Origin::BottomLeft => (left, bottom),
+            Origin::BottomRight => (w - left, bottom),
+            Origin::TopLeft => (left, h - bottom),
+            Origin::TopRight => (w - left, h - bottom),
+        };
+        Point2::new(x, y) * self.units.per_mm()
+    }
+
+    fn pen_up(&self, out: &mut impl Write) -> io::Result<()> {
+        match &self.pen {
+            PenControl::Z { up, .. } => writeln!(out, "G0 Z{:.3}", up * self.units.per_mm()),
+            PenControl::Servo { up, dwell, .. } => {
+                writeln!(out, "{}", up)?;
+                self.dwell(out, *dwell)
+            }
+        }
+    }
+
+    fn pen_down(&self, out: &mut impl Write) -> io::Result<()> {
+        match &self.pen {
+            PenControl::Z { down, .. } => writeln!(out, "G0 Z{:.3}", down * self.units.per_mm()),
+            PenControl::Servo { down, dwell, .. } => {
+                writeln!(out, "{}", down)?;
+                self.dwell(out, *dwell)
+            }
+        }
+    }
+
+    /// Pause for `seconds` with `G4`, giving `P` in seconds (GRBL) or,
+    /// with `dwell_milliseconds`, milliseconds (Marlin).
+    fn dwell(&self, out: &mut impl Write, seconds: f64) -> io::Result<()> {
+        if seconds > 0.0 {
+            if self.dwell_milliseconds {
+                writeln!(out, "G4 P{:.0}", seconds * 1000.0)?;
+            } else {
+                writeln!(out, "G4 P{:.3}", seconds)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move to a point with the pen up.
+    fn travel(&self, out: &mut impl Write, p: &Point2<f64>) -> io::Result<()> {
+        match self.travel_feed {
+            Some(feed) => writeln!(
+                out,
+                "G1 X{:.3} Y{:.3} F{:.1}",
+                p.x,
+                p.y,
+                feed * self.units.per_mm()
+            ),
+            None => writeln!(out, "G0 X{:.3} Y{:.3}", p.x, p.y),
+        }
+    }
+
+    /// Write the G-code drawing `paths`, exactly in the order given.
+    ///
+    /// The G-code pauses for a pen change whenever a path's pen
+    /// differs from the one before it, so the paths should already be
+    /// grouped by pen, as `render_scene` and `PathOrder`
+    /// leave them.
+    pub fn write(&self, out: &mut impl Write, paths: &[RenderedPath]) -> io::Result<()> {
+        match self.units {
+            Units::Millimeters => writeln!(out, "G21")?,
+            Units::Inches => writeln!(out, "G20")?,
+            Units::Unset(_) => {}
+        }
+        writeln!(out, "G90")?;
+        self.pen_up(out)?;
+
+        let mut pen = None;
+        for path in paths.iter().filter(|p| p.points.len() > 1) {
+            if pen != Some(path.shape.style.pen) {
+                if pen.is_some() {
+                    writeln!(out, "M0 (change to pen {})", path.shape.style.pen)?;
+                } else {
+                    writeln!(out, "(pen {})", path.shape.style.pen)?;
+                }
+                pen = Some(path.shape.style.pen);
+            }
+
+            let mut points = path.points.iter().map(|p| self.machine_point(p));
+            self.travel(out, &points.next().unwrap())?;
+            self.pen_down(out)?;
+            writeln!(out, "G1 F{:.1}", self.draw_feed * self.units.per_mm())?;
+            for p in points {
+                writeln!(out, "G1 X{:.3} Y{:.3}", p.x, p.y)?;
+            }
+            self.pen_up(out)?;
+        }
+
+        self.travel(out, &Point2::origin())?;
+        writeln!(out, "M2")
+    }
+
+    /// Return the G-code drawing `paths`, as with `write`.
+    pub fn to_gcode(&self, paths: &[RenderedPath]) -> String {
+        let mut out = vec![];
+        self.write(&mut out, paths)
+            .expect("writing to a Vec can't fail");
+        String::from_utf8(out).expect("G-code is valid UTF-8")
+    }
+
+    /// Return the G-code drawing the paths of `scene` visible from
+    /// `camera`, one pen at a time, otherwise in the order rendered.
+    ///
+    /// Reordering the paths first, with `PathOrder`, saves much of the
+    /// time spent traveling between them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any path is too degenerate to render, as with
+    /// `Scene::render`.
+    pub fn render_scene(&self, scene: &Scene, camera: &Camera) -> String {
+        self.try_render_scene(scene, camera)
+            .unwrap_or_else(|e| panic!("could not render scene: {}", e))
+    }
+
+    /// Return the G-code drawing the paths of `scene` as with
+    /// `render_scene`, leaving out paths too degenerate to render as
+    /// with `Scene::try_render`.
+    pub fn try_render_scene(
+        &self,
+        scene: &Scene,
+        camera: &Camera,
+    ) -> Result<String, PartialRender<String>> {
+        let to_gcode = |mut paths: Vec<RenderedPath>| {
+            group_by_pen(&mut paths);
+            self.to_gcode(&paths)
+        };
+        match scene.try_render(camera) {
+            Ok(paths) => Ok(to_gcode(paths)),
+            Err(partial) => Err(partial.map(to_gcode)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::test_path;
+
+    #[test]
+    fn writes_paths() {
+        let paths = vec![
+            test_path(1, &[(-1.0, -1.0), (1.0, 1.0)]),
+            test_path(2, &[(-0.5, 0.5), (0.5, 0.5), (0.5, -0.5)]),
+        ];
+
+        let gcode = GcodeWriter::new(200.0, 100.0).to_gcode(&paths);
+        let lines: Vec<_> = gcode.lines().collect();
+        assert_eq!(&lines[..3], &["G21", "G90", "G0 Z5.000"]);
+        assert!(gcode.contains("G0 X0.000 Y0.000\nG0 Z0.000\nG1 F1000.0\nG1 X200.000 Y100.000\n"));
+        assert!(gcode.contains("M0 (change to pen 2)\nG0 X50.000 Y75.000\n"));
+        assert_eq!(lines[lines.len() - 1], "M2");
+
+        // Servo pens, from the top right, on a machine already set to
+        // count in tenths of a millimeter.
+        let gcode = GcodeWriter::new(200.0, 100.0)
+            .pen(PenControl::Servo {
+                up: "M5".to_string(),
+                down: "M3 S30".to_string(),
+                dwell: 0.25,
+            })
+            .origin(Origin::TopRight)
+            .units(Units::Unset(10.0))
+            .travel_feed(3000.0)
+            .to_gcode(&paths[1..]);
+        assert_eq!(gcode.lines().next(), Some("G90"));
+        assert!(!gcode.contains(" Z"));
+        assert!(gcode.contains("G1 X1500.000 Y250.000 F30000.0\nM3 S30\nG4 P0.250\n"));
+        assert!(gcode.contains("G1 X500.000 Y750.000\nM5\n"));
+
+        // Inches, with Marlin's millisecond dwell times.
+        let gcode = GcodeWriter::new(254.0, 127.0)
+            .pen(PenControl::Servo {
+                up: "M5".to_string(),
+                down: "M3 S30".to_string(),
+                dwell: 0.25,
+            })
+            .units(Units::Inches)
+            .dwell_milliseconds(true)
+            .to_gcode(&paths[..1]);
+        let lines: Vec<_> = gcode.lines().collect();
+        assert_eq!(&lines[..2], &["G20", "G90"]);
+        assert!(gcode.contains("G0 X0.000 Y0.000\nM3 S30\nG4 P250\nG1 F39.4\nG1 X10.000 Y5.000\n"));
+    }
+}
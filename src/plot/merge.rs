@@ -0,0 +1,186 @@
+//! Join paths whose ends meet into longer polylines.
+use super::compatible;
+use crate::common::*;
+use crate::scene::RenderedPath;
+use crate::shape::assert_spacing;
+use std::collections::HashMap;
+
+/// Merging of rendered paths whose endpoints coincide, so that they
+/// are drawn without lifting the pen.
+///
+/// Only paths drawn with the same style are joined, or with the same
+/// pen, if `pen_only` is set. A merged path keeps the shape identity
+/// of the first path in it.
+#[derive(Clone, Debug)]
+pub struct PathMerge {
+    /// maximum distance between the ends joined
+    tolerance: f64,
+
+    /// whether paths with different styles on the same pen are joined
+    pen_only: bool,
+}
+
+/// End of a path, by index, and whether it is the last point.
+#[derive(Clone, Copy, PartialEq)]
+struct End {
+    index: usize,
+    last: bool,
+}
+
+/// Spatial hash of path endpoints, with cells the size of the
+/// tolerance.
+struct EndGrid {
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<End>>,
+}
+
+impl EndGrid {
+    fn key(&self, p: &Point2<f64>) -> (i64, i64) {
+        (
+            (p.x / self.cell).floor() as i64,
+            (p.y / self.cell).floor() as i64,
+        )
+    }
+
+    fn insert(&mut self, p: &Point2<f64>, end: End) {
+        let key = self.key(p);
+        self.cells.entry(key).or_default().push(end);
+    }
+
+    /// Return the ends in the cells around `p`.
+    fn near(&self, p: &Point2<f64>) -> impl Iterator<Item = End> + '_ {
+        let (x, y) = self.key(p);
+        (-1..=1)
+            .flat_map(move |i| (-1..=1).map(move |j| (x + i, y + j)))
+            .filter_map(move |key| self.cells.get(&key))
+            .flatten()
+            .copied()
+    }
+}
+
+impl PathMerge {
+    /// Create a merge joining ends within `tolerance` of each other,
+    /// in NDC.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` isn't positive and finite.
+    pub fn new(tolerance: f64) -> PathMerge {
+        assert_spacing(tolerance, "merge tolerance");
+        PathMerge {
+            tolerance,
+            pen_only: false,
+        }
+    }
+
+    /// Return a modified version of the merge, joining paths with the
+    /// same pen regardless of the rest of their style if `pen_only`
+    /// is true.
+    pub fn pen_only(self, pen_only: bool) -> PathMerge {
+        PathMerge { pen_only, ..self }
+    }
+
+    /// Join the paths end to end wherever they meet, returning the
+    /// merged paths in the order of their first path.
+    pub fn apply(&self, paths: Vec<RenderedPath>) -> Vec<RenderedPath> {
+        let mut grid = EndGrid {
+            cell: self.tolerance,
+            cells: HashMap::new(),
+        };
+        for (index, p) in paths.iter().enumerate() {
+            if let (Some(first), Some(last)) = (p.points.first(), p.points.last()) {
+                grid.insert(first, End { index, last: false });
+                grid.insert(last, End { index, last: true });
+            }
+        }
+
+        let mut used = vec![false; paths.len()];
+        let mut merged = vec![];
+        for index in 0..paths.len() {
+            if used[index] {
+                continue;
+            }
+            used[index] = true;
+            let mut chain = paths[index].clone();
+            if chain.points.is_empty() {
+                merged.push(chain);
+                continue;
+            }
+
+            // Extend the end of the chain, then flip it around and
+            // extend the other end.
+            for _ in 0..2 {
+                while let Some(next) = self.find_next(&paths, &grid, &used, &chain) {
+                    used[next.index] = true;
+                    let points = &paths[next.index].points;
+                    if next.last {
+                        chain.points.extend(points.iter().rev().skip(1));
+                    } else {
+                        chain.points.extend(points.iter().skip(1));
+                    }
+                }
+                chain.points.reverse();
+            }
+            merged.push(chain);
+        }
+        merged
+    }
+
+    /// Return the nearest unused end meeting the end of `chain`, if
+    /// any.
+    fn find_next(
+        &self,
+        paths: &[RenderedPath],
+        grid: &EndGrid,
+        used: &[bool],
+        chain: &RenderedPath,
+    ) -> Option<End> {
+        let p = chain.points[chain.points.len() - 1];
+        grid.near(&p)
+            .filter(|end| !used[end.index] && compatible(chain, &paths[end.index], self.pen_only))
+            .map(|end| {
+                let points = &paths[end.index].points;
+                let q = if end.last {
+                    points[points.len() - 1]
+                } else {
+                    points[0]
+                };
+                (end, (q - p).norm())
+            })
+            .filter(|(_, d)| *d <= self.tolerance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(end, _)| end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::test_path;
+
+    #[test]
+    fn joins_matching_ends() {
+        // The sides of a square, out of order and direction, with a
+        // gap within the tolerance, and a diagonal on another pen.
+        let paths = vec![
+            test_path(1, &[(0.0, 0.0), (1.0, 0.0)]),
+            test_path(1, &[(0.0, 1.0), (1.0, 1.0)]),
+            test_path(2, &[(0.0, 0.0), (1.0, 1.0)]),
+            test_path(1, &[(1.0, 1.0 + 1e-7), (1.0, 0.0)]),
+        ];
+
+        let merged = PathMerge::new(1e-6).apply(paths.clone());
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].points.len(), 4);
+        assert_eq!(merged[1].shape.style.pen, 2);
+
+        let unmerged = PathMerge::new(1e-8).apply(paths);
+        assert_eq!(unmerged.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "merge tolerance must be positive")]
+    fn zero_merge_tolerance() {
+        PathMerge::new(0.0);
+    }
+}
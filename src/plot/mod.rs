@@ -0,0 +1,56 @@
+//! Post-processing of rendered paths for output to pen plotters.
+mod dedupe;
+mod gcode;
+mod merge;
+mod order;
+mod simplify;
+
+pub use dedupe::SegmentDedupe;
+pub use gcode::{GcodeWriter, Origin, PenControl, Units};
+pub use merge::PathMerge;
+pub use order::{travel_distance, PathOrder, TravelReport};
+pub use simplify::PathSimplify;
+
+use crate::scene::RenderedPath;
+
+/// Stably sort `paths` so that those drawn with each pen are
+/// together, pen by pen in the order the pens first appear.
+pub(crate) fn group_by_pen(paths: &mut [RenderedPath]) {
+    let mut pens = vec![];
+    for p in paths.iter() {
+        if !pens.contains(&p.shape.style.pen) {
+            pens.push(p.shape.style.pen);
+        }
+    }
+    paths.sort_by_key(|p| pens.iter().position(|&pen| pen == p.shape.style.pen));
+}
+
+/// Return true if the paths are drawn the same way: with the same
+/// style, or just the same pen if `pen_only`.
+fn compatible(a: &RenderedPath, b: &RenderedPath, pen_only: bool) -> bool {
+    if pen_only {
+        a.shape.style.pen == b.shape.style.pen
+    } else {
+        a.shape.style == b.shape.style
+    }
+}
+
+/// Return a path through `points`, drawn with `pen`, for testing.
+#[cfg(test)]
+fn test_path(pen: u32, points: &[(f64, f64)]) -> RenderedPath {
+    use crate::common::*;
+    use crate::scene::{ShapeInfo, Style};
+    use std::sync::Arc;
+
+    RenderedPath {
+        shape: Arc::new(ShapeInfo {
+            index: 0,
+            name: "Test".to_string(),
+            tag: None,
+            style: Style::new().pen(pen),
+            occluding: true,
+            drawn: true,
+        }),
+        points: points.iter().map(|&(x, y)| Point2::new(x, y)).collect(),
+    }
+}
@@ -0,0 +1,383 @@
+//! Reorder and reverse paths to reduce the distance a plotter travels
+//! with the pen up.
+use super::group_by_pen;
+use crate::common::*;
+use crate::scene::RenderedPath;
+
+/// Reordering of rendered paths to reduce pen-up travel between them.
+///
+/// Paths are first ordered greedily, always drawing the nearest
+/// remaining path next, and the order is then improved with 2-opt
+/// moves, reversing runs of paths wherever that shortens the travel.
+/// Both are quadratic in the number of paths.
+///
+/// Paths for each pen are ordered separately and drawn together, pen
+/// by pen in the order the pens first appear. If that travels further
+/// than drawing each pen's paths in their original order, the
+/// original order is kept.
+#[derive(Clone, Debug)]
+pub struct PathOrder {
+    /// whether paths may be drawn from their end to their start
+    reverse: bool,
+
+    /// maximum number of 2-opt passes over the paths
+    max_passes: usize,
+
+    /// position of the pen before the first path
+    start: Option<Point2<f64>>,
+}
+
+/// Pen-up travel distance before and after reordering paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TravelReport {
+    pub before: f64,
+    pub after: f64,
+}
+
+impl TravelReport {
+    /// Return the travel distance saved by reordering.
+    pub fn saved(&self) -> f64 {
+        self.before - self.after
+    }
+}
+
+/// Return the pen-up distance traveled drawing `paths` in order,
+/// starting from `start` if given, or else from the first path.
+pub fn travel_distance(paths: &[RenderedPath], start: Option<Point2<f64>>) -> f64 {
+    let mut pos = start;
+    let mut distance = 0.0;
+    for p in paths.iter().filter(|p| !p.points.is_empty()) {
+        if let Some(pos) = pos {
+            distance += (p.points[0] - pos).norm();
+        }
+        pos = p.points.last().copied();
+    }
+    distance
+}
+
+/// A path in the new order, by index, drawn backwards if reversed.
+#[derive(Clone, Copy)]
+struct Step {
+    index: usize,
+    reversed: bool,
+}
+
+impl PathOrder {
+    /// Create an ordering that may reverse paths, running up to 20
+    /// passes of 2-opt.
+    pub fn new() -> PathOrder {
+        PathOrder {
+            reverse: true,
+            max_passes: 20,
+            start: None,
+        }
+    }
+
+    /// Return a modified version of the ordering, only reversing paths
+    /// if `reverse` is true.
+    pub fn reverse(self, reverse: bool) -> PathOrder {
+        PathOrder { reverse, ..self }
+    }
+
+    /// Return a modified version of the ordering, with at most
+    /// `max_passes` passes of 2-opt improvement.
+    ///
+    /// Each pass is quadratic in the number of paths, and passes stop
+    /// early once they no longer improve the order.
+    pub fn max_passes(self, max_passes: usize) -> PathOrder {
+        PathOrder { max_passes, ..self }
+    }
+
+    /// Return a modified version of the ordering, with the pen
+    /// starting at `start`.
+    ///
+    /// Otherwise, the first path in the original order is drawn first.
+    pub fn start(self, start: Point2<f64>) -> PathOrder {
+        PathOrder {
+            start: Some(start),
+            ..self
+        }
+    }
+
+    /// Reorder `paths`, returning them along with the travel saved.
+    ///
+    /// Unless the input switches back and forth between pens, the
+    /// travel is never longer than before.
+    pub fn apply(&self, paths: Vec<RenderedPath>) -> (Vec<RenderedPath>, TravelReport) {
+        let before = travel_distance(&paths, self.start);
+
+        let (mut paths, empty): (Vec<_>, Vec<_>) =
+            paths.into_iter().partition(|p| !p.points.is_empty());
+
+        group_by_pen(&mut paths);
+
+        let mut steps = Vec::with_capacity(paths.len());
+        let mut pos = self.start;
+        let mut first = 0;
+        while first < paths.len() {
+            let pen = paths[first].shape.style.pen;
+            let count = paths[first..]
+                .iter()
+                .take_while(|p| p.shape.style.pen == pen)
+                .count();
+            let group = &paths[first..first + count];
+            let mut group_steps = self.nearest_neighbor(group, pos);
+            self.two_opt(group, &mut group_steps, pos);
+
+            pos = Some(end(group, group_steps[count - 1]));
+            steps.extend(group_steps.into_iter().map(|step| Step {
+                index: step.index + first,
+                ..step
+            }));
+            first += count;
+        }
+
+        let unordered = travel_distance(&paths, self.start);
+        if steps_travel(&paths, &steps, self.start) > unordered {
+            steps = (0..paths.len())
+                .map(|index| Step {
+                    index,
+                    reversed: false,
+                })
+                .collect();
+        }
+
+        let mut slots: Vec<Option<RenderedPath>> = paths.drain(..).map(Some).collect();
+        let mut ordered: Vec<RenderedPath> = steps
+            .iter()
+            .map(|step| {
+                let mut path = slots[step.index].take().unwrap();
+                if step.reversed {
+                    path.points.reverse();
+                }
+                path
+            })
+            .collect();
+        ordered.extend(empty);
+
+        let after = travel_distance(&ordered, self.start);
+        (ordered, TravelReport { before, after })
+    }
+
+    /// Order the paths greedily, drawing the nearest one to the pen,
+    /// starting from `pos`, next.
+    fn nearest_neighbor(&self, paths: &[RenderedPath], mut pos: Option<Point2<f64>>) -> Vec<Step> {
+        let mut remaining: Vec<usize> = (0..paths.len()).collect();
+        let mut steps = Vec::with_capacity(paths.len());
+
+        while !remaining.is_empty() {
+            let (k, step) = match pos {
+                None => (
+                    0,
+                    Step {
+                        index: remaining[0],
+                        reversed: false,
+                    },
+                ),
+                Some(pos) => {
+                    let mut best = (0, remaining[0], false, f64::INFINITY);
+                    for (k, &index) in remaining.iter().enumerate() {
+                        let points = &paths[index].points;
+                        let to_start = (points[0] - pos).norm_squared();
+                        if to_start < best.3 {
+                            best = (k, index, false, to_start);
+                        }
+                        let to_end = (points[points.len() - 1] - pos).norm_squared();
+                        if self.reverse && to_end < best.3 {
+                            best = (k, index, true, to_end);
+                        }
+                    }
+                    (
+                        best.0,
+                        Step {
+                            index: best.1,
+                            reversed: best.2,
+                        },
+                    )
+                }
+            };
+            remaining.swap_remove(k);
+            pos = Some(end(paths, step));
+            steps.push(step);
+        }
+        steps
+    }
+
+    /// Improve the order by reversing runs of steps, flipping the
+    /// paths in the run if reversing is allowed, while that shortens
+    /// the travel from `pos`.
+    fn two_opt(&self, paths: &[RenderedPath], steps: &mut [Step], pos: Option<Point2<f64>>) {
+        // Ignore improvements too small to matter, so that rounding
+        // can't cause endless passes.
+        const EPSILON: f64 = 1e-12;
+        let flip = |step: Step| Step {
+            reversed: step.reversed ^ self.reverse,
+            ..step
+        };
+        let travel = |from: Step, to: Step| (start(paths, to) - end(paths, from)).norm();
+
+        // Without a start position, the first path stays first.
+        let first = if pos.is_some() { 0 } else { 1 };
+        for _ in 0..self.max_passes {
+            let mut improved = false;
+            for a in first..steps.len() {
+                // Travel within the run, forwards and reversed.
+                let (mut inside, mut inside_reversed) = (0.0, 0.0);
+                for b in a..steps.len() {
+                    if b > a {
+                        inside += travel(steps[b - 1], steps[b]);
+                        inside_reversed += travel(flip(steps[b]), flip(steps[b - 1]));
+                    }
+                    if b == a && !self.reverse {
+                        continue;
+                    }
+
+                    let (first_step, last_step) = (flip(steps[b]), flip(steps[a]));
+                    let (mut old, mut new) = (inside, inside_reversed);
+                    if a > 0 {
+                        old += travel(steps[a - 1], steps[a]);
+                        new += travel(steps[a - 1], first_step);
+                    } else if let Some(pos) = pos {
+                        old += (start(paths, steps[a]) - pos).norm();
+                        new += (start(paths, first_step) - pos).norm();
+                    }
+                    if b + 1 < steps.len() {
+                        old += travel(steps[b], steps[b + 1]);
+                        new += travel(last_step, steps[b + 1]);
+                    }
+
+                    if new < old - EPSILON {
+                        steps[a..=b].reverse();
+                        for step in &mut steps[a..=b] {
+                            *step = flip(*step);
+                        }
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+}
+
+impl Default for PathOrder {
+    fn default() -> PathOrder {
+        PathOrder::new()
+    }
+}
+
+/// Return the pen-up distance traveled drawing `paths` in the order
+/// of `steps`, starting from `from` if given.
+fn steps_travel(paths: &[RenderedPath], steps: &[Step], from: Option<Point2<f64>>) -> f64 {
+    let mut pos = from;
+    let mut distance = 0.0;
+    for &step in steps {
+        if let Some(pos) = pos {
+            distance += (start(paths, step) - pos).norm();
+        }
+        pos = Some(end(paths, step));
+    }
+    distance
+}
+
+/// Return the first point drawn for a step.
+fn start(paths: &[RenderedPath], step: Step) -> Point2<f64> {
+    let points = &paths[step.index].points;
+    if step.reversed {
+        points[points.len() - 1]
+    } else {
+        points[0]
+    }
+}
+
+/// Return the last point drawn for a step.
+fn end(paths: &[RenderedPath], step: Step) -> Point2<f64> {
+    let points = &paths[step.index].points;
+    if step.reversed {
+        points[0]
+    } else {
+        points[points.len() - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::test_path;
+
+    /// Return a row of short segments along the x-axis, alternately
+    /// pointing backwards, in a scrambled order.
+    fn scattered_segments() -> Vec<RenderedPath> {
+        [3, 0, 5, 1, 4, 2]
+            .iter()
+            .map(|&i| {
+                let x = i as f64 * 0.2;
+                if i % 2 == 1 {
+                    test_path(1, &[(x + 0.1, 0.0), (x, 0.0)])
+                } else {
+                    test_path(1, &[(x, 0.0), (x + 0.1, 0.0)])
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_scattered_segments() {
+        let paths = scattered_segments();
+        let start = Point2::new(-0.1, 0.0);
+        let (ordered, report) = PathOrder::new().start(start).apply(paths.clone());
+        assert_eq!(ordered.len(), paths.len());
+        assert!(report.saved() > 0.0);
+        assert!((report.after - 0.1 * 6.0).abs() < 1e-9);
+        for (i, p) in ordered.iter().enumerate() {
+            assert!((p.points[0].x - i as f64 * 0.2).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn orders_without_reversing() {
+        let paths = scattered_segments();
+        let (ordered, report) = PathOrder::new().reverse(false).apply(paths.clone());
+        assert_eq!(ordered.len(), paths.len());
+        assert!(report.after <= report.before);
+        assert!(paths
+            .iter()
+            .all(|p| ordered.iter().any(|q| q.points == p.points)));
+        assert!((report.after - travel_distance(&ordered, None)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn orders_within_pens() {
+        // Alternating pens are drawn one pen at a time, the second
+        // starting from where the first left off.
+        let paths: Vec<_> = (0..6)
+            .map(|i| {
+                let x = i as f64 * 0.2;
+                test_path(1 + i % 2, &[(x, 0.0), (x + 0.1, 0.0)])
+            })
+            .collect();
+        let (ordered, _) = PathOrder::new().apply(paths);
+        let pens: Vec<_> = ordered.iter().map(|p| p.shape.style.pen).collect();
+        assert_eq!(pens, vec![1, 1, 1, 2, 2, 2]);
+        assert_eq!(ordered[3].points[0].x, 1.0);
+    }
+
+    #[test]
+    fn keeps_better_original_order() {
+        // Drawing the nearest path first leaves the one behind the
+        // start for last, so the original order is better and kept.
+        let xs = [0.0, -1.2, 1.0, 2.0, 3.0];
+        let paths: Vec<_> = xs
+            .iter()
+            .map(|&x| test_path(1, &[(x, 0.0), (x + 0.1, 0.0)]))
+            .collect();
+        let (ordered, report) = PathOrder::new().reverse(false).apply(paths);
+        assert_eq!(report.saved(), 0.0);
+        for (p, &x) in ordered.iter().zip(&xs) {
+            assert_eq!(p.points[0].x, x);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Remove points that don't visibly change the shape of paths.
+use crate::common::*;
+use crate::scene::{RenderPath, RenderedPath};
+use crate::shape::assert_spacing;
+
+/// Ramer–Douglas–Peucker simplification of rendered paths.
+///
+/// Each path keeps its ends, and only drops points within `tolerance`
+/// of the simplified path, so choosing a tolerance below the size of
+/// a pixel leaves the drawing unchanged.
+#[derive(Clone, Debug)]
+pub struct PathSimplify {
+    /// maximum distance of a dropped point from the simplified path
+    tolerance: f64,
+}
+
+impl PathSimplify {
+    /// Create a simplification dropping points within `tolerance`, in
+    /// NDC.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` isn't positive and finite.
+    pub fn new(tolerance: f64) -> PathSimplify {
+        assert_spacing(tolerance, "simplify tolerance");
+        PathSimplify { tolerance }
+    }
+
+    /// Simplify each of the paths.
+    pub fn apply(&self, paths: Vec<RenderedPath>) -> Vec<RenderedPath> {
+        paths
+            .into_iter()
+            .map(|p| RenderedPath {
+                points: self.simplify(&p.points),
+                ..p
+            })
+            .collect()
+    }
+
+    /// Return the points of a path that are kept.
+    pub fn simplify(&self, points: &[Point2<f64>]) -> RenderPath {
+        if points.len() < 3 {
+            return points.to_vec();
+        }
+
+        let mut keep = vec![false; points.len()];
+        keep[0] = true;
+        keep[points.len() - 1] = true;
+
+        // Split each span at its farthest point, until every point is
+        // close enough to the span containing it.
+        let mut spans = vec![(0, points.len() - 1)];
+        while let Some((first, last)) = spans.pop() {
+            let farthest = (first + 1..last)
+                .map(|i| (i, distance(&points[i], &points[first], &points[last])))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((i, d)) = farthest {
+                if d > self.tolerance {
+                    keep[i] = true;
+                    spans.push((first, i));
+                    spans.push((i, last));
+                }
+            }
+        }
+
+        points
+            .iter()
+            .zip(keep)
+            .filter(|(_, k)| *k)
+            .map(|(p, _)| *p)
+            .collect()
+    }
+}
+
+/// Return the distance from `p` to the segment from `a` to `b`.
+fn distance(p: &Point2<f64>, a: &Point2<f64>, b: &Point2<f64>) -> f64 {
+    let d = b - a;
+    let length_sq = d.norm_squared();
+    if length_sq == 0.0 {
+        return (p - a).norm();
+    }
+    let t = ((p - a).dot(&d) / length_sq).clamp(0.0, 1.0);
+    (p - (a + d * t)).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplifies_paths() {
+        let simplify = PathSimplify::new(1e-3);
+
+        // Evenly split segments lose their inner points.
+        let line: Vec<_> = (0..=10)
+            .map(|i| Point2::new(i as f64 * 0.1, 0.5 * i as f64 * 0.1))
+            .collect();
+        assert_eq!(simplify.simplify(&line), vec![line[0], line[10]]);
+
+        // A closed circle keeps enough points to stay within the
+        // tolerance, and both of its ends.
+        let circle: Vec<_> = (0..=1000)
+            .map(|i| {
+                let a = std::f64::consts::TAU * i as f64 / 1000.0;
+                Point2::new(a.cos(), a.sin())
+            })
+            .collect();
+        let simple = simplify.simplify(&circle);
+        assert!(simple.len() < circle.len() && simple.len() > 8);
+        assert_eq!(simple[0], circle[0]);
+        assert_eq!(simple[simple.len() - 1], circle[1000]);
+        for p in &circle {
+            let d = simple
+                .windows(2)
+                .map(|w| distance(p, &w[0], &w[1]))
+                .fold(f64::INFINITY, f64::min);
+            assert!(d <= 1e-3);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "simplify tolerance must be positive")]
+    fn zero_simplify_tolerance() {
+        PathSimplify::new(0.0);
+    }
+}
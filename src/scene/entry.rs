@@ -0,0 +1,135 @@
+//! Entries added to a scene, and the identity of the shapes they
+//! produce.
+use super::{Group, Style};
+use crate::common::*;
+use crate::shape::Shape;
+use std::sync::Arc;
+
+/// Shape, or group of shapes, to add to a scene, along with
+/// attributes that apply to every shape it contains.
+pub struct SceneEntry {
+    content: EntryContent,
+
+    /// user tag attached to the rendered paths
+    tag: Option<String>,
+
+    style: Style,
+
+    /// whether the shapes hide what is behind them
+    occluding: bool,
+
+    /// whether the paths of the shapes are rendered
+    drawn: bool,
+}
+
+enum EntryContent {
+    Shape(Box<dyn Shape>),
+    Group(Arc<Group>),
+}
+
+impl SceneEntry {
+    /// Create an entry for a single shape.
+    pub fn new<S: Shape + 'static>(shape: S) -> SceneEntry {
+        SceneEntry {
+            content: EntryContent::Shape(Box::new(shape)),
+            tag: None,
+            style: Style::new(),
+            occluding: true,
+            drawn: true,
+        }
+    }
+
+    /// Create an entry for a group of shapes.
+    pub fn group(group: Group) -> SceneEntry {
+        SceneEntry::instance(Arc::new(group))
+    }
+
+    /// Create an entry for an instance of a shared group.
+    pub fn instance(group: Arc<Group>) -> SceneEntry {
+        SceneEntry {
+            content: EntryContent::Group(group),
+            tag: None,
+            style: Style::new(),
+            occluding: true,
+            drawn: true,
+        }
+    }
+
+    /// Return a modified version of the entry, tagging the paths
+    /// rendered from its shapes with `tag`.
+    pub fn tag<T: Into<String>>(self, tag: T) -> SceneEntry {
+        SceneEntry {
+            tag: Some(tag.into()),
+            ..self
+        }
+    }
+
+    /// Return a modified version of the entry, drawing its shapes
+    /// with `style`.
+    pub fn style(self, style: Style) -> SceneEntry {
+        SceneEntry { style, ..self }
+    }
+
+    /// Return a modified version of the entry whose shapes are drawn
+    /// without hiding anything behind them, such as ghosted reference
+    /// geometry.
+    pub fn non_occluding(self) -> SceneEntry {
+        SceneEntry {
+            occluding: false,
+            ..self
+        }
+    }
+
+    /// Return a modified version of the entry whose shapes hide what
+    /// is behind them without being drawn themselves.
+    pub fn occlude_only(self) -> SceneEntry {
+        SceneEntry {
+            drawn: false,
+            ..self
+        }
+    }
+
+    /// Append the shapes of the entry to `shapes`, with the identity
+    /// of each appended to `infos`.
+    pub(crate) fn flatten(self, shapes: &mut Vec<Box<dyn Shape>>, infos: &mut Vec<Arc<ShapeInfo>>) {
+        let first = shapes.len();
+        match self.content {
+            EntryContent::Shape(shape) => shapes.push(shape),
+            EntryContent::Group(group) => group.flatten(&Matrix4::identity(), shapes),
+        }
+        for (index, shape) in shapes.iter().enumerate().skip(first) {
+            infos.push(Arc::new(ShapeInfo {
+                index,
+                name: shape.name(),
+                tag: self.tag.clone(),
+                style: self.style.clone(),
+                occluding: self.occluding,
+                drawn: self.drawn,
+            }));
+        }
+    }
+}
+
+/// Identity of a shape in a scene, attached to the paths rendered
+/// from it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeInfo {
+    /// position of the shape in the scene, counting each shape of a
+    /// group separately, in the order added
+    pub index: usize,
+
+    /// name of the shape, from `Shape::name`
+    pub name: String,
+
+    /// tag given to the entry the shape was added with
+    pub tag: Option<String>,
+
+    /// style the shape is drawn with
+    pub style: Style,
+
+    /// false if the shape hides nothing behind it
+    pub occluding: bool,
+
+    /// false if the paths of the shape are not rendered
+    pub drawn: bool,
+}
@@ -0,0 +1,166 @@
+//! Hierarchical groups of shapes, for building scenes out of reusable
+//! parts.
+use crate::common::*;
+use crate::shape::{Shape, TransformedShape};
+use std::sync::Arc;
+
+/// Child of a group: either a shape, or a nested group.
+#[derive(Clone)]
+enum Node {
+    Shape(Arc<dyn Shape>),
+    Group(Arc<Group>),
+}
+
+/// Collection of shapes and nested groups sharing a transform.
+///
+/// Groups are shared through `Arc`, so a subassembly (say, a chair)
+/// can be built once and placed many times with `add_instance`,
+/// without duplicating its geometry. The hierarchy is flattened into
+/// individual shapes when the scene is built.
+#[derive(Clone)]
+pub struct Group {
+    /// transform from the space of the children to the space of the
+    /// parent
+    transform: Matrix4<f64>,
+
+    children: Vec<Node>,
+}
+
+impl Group {
+    /// Create an empty group with no transform.
+    pub fn new() -> Group {
+        Group {
+            transform: Matrix4::identity(),
+            children: vec![],
+        }
+    }
+
+    /// Return a modified version of the group, placed rigidly with
+    /// `transform`.
+    pub fn transform(self, transform: &Isometry3<f64>) -> Group {
+        self.matrix(transform.to_homogeneous())
+    }
+
+    /// Return a modified version of the group, placed with a general
+    /// affine `transform`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform is not affine or can't be inverted.
+    pub fn matrix(self, transform: Matrix4<f64>) -> Group {
+        assert!(
+            transform.fixed_rows::<na::U1>(3) == Matrix4::identity().fixed_rows::<na::U1>(3)
+                && transform.is_invertible(),
+            "group transform must be an invertible affine transform"
+        );
+        Group { transform, ..self }
+    }
+
+    /// Add a single shape.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<S: Shape + 'static>(self, shape: S) -> Group {
+        self.add_shared(Arc::new(shape))
+    }
+
+    /// Add a shape that may also be used elsewhere.
+    pub fn add_shared(mut self, shape: Arc<dyn Shape>) -> Group {
+        self.children.push(Node::Shape(shape));
+        self
+    }
+
+    /// Add a nested group.
+    pub fn add_group(self, group: Group) -> Group {
+        self.add_instance(Arc::new(group))
+    }
+
+    /// Add an instance of a shared group.
+    ///
+    /// To place the same group in several places, wrap each instance
+    /// in a group of its own with the desired transform.
+    pub fn add_instance(mut self, group: Arc<Group>) -> Group {
+        self.children.push(Node::Group(group));
+        self
+    }
+
+    /// Return the number of shapes in the group, counting every
+    /// instance separately.
+    pub fn num_shapes(&self) -> usize {
+        self.children
+            .iter()
+            .map(|c| match c {
+                Node::Shape(_) => 1,
+                Node::Group(g) => g.num_shapes(),
+            })
+            .sum()
+    }
+
+    /// Append the shapes of the group, placed in world space, to
+    /// `shapes`, given the transform of the parent into world space.
+    pub(crate) fn flatten(&self, parent: &Matrix4<f64>, shapes: &mut Vec<Box<dyn Shape>>) {
+        let transform = parent * self.transform;
+        for child in &self.children {
+            match child {
+                Node::Shape(shape) if transform == Matrix4::identity() => {
+                    shapes.push(Box::new(shape.clone()))
+                }
+                Node::Shape(shape) => shapes.push(Box::new(
+                    TransformedShape::from_matrix(shape.clone(), transform)
+                        .expect("group transforms are invertible and affine"),
+                )),
+                Node::Group(group) => group.flatten(&transform, shapes),
+            }
+        }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Group {
+        Group::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::BoxOutline;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn instanced_groups() {
+        // a "table" of a top and one leg, all in local space
+        let table = Arc::new(
+            Group::new()
+                .add(BoxOutline::new(
+                    Point3::new(0.0, 1.0, 0.0),
+                    Vector3::new(1.0, 0.1, 1.0),
+                ))
+                .add(BoxOutline::new(
+                    Point3::new(0.0, 0.5, 0.0),
+                    Vector3::new(0.1, 0.5, 0.1),
+                )),
+        );
+
+        let room = (0..3).fold(Group::new(), |room, i| {
+            room.add_group(
+                Group::new()
+                    .transform(&Isometry3::translation(i as f64 * 3.0, 0.0, 0.0))
+                    .add_instance(table.clone()),
+            )
+        });
+        let room = Group::new()
+            .transform(&Isometry3::translation(0.0, 0.0, -5.0))
+            .add_group(room);
+        assert_eq!(room.num_shapes(), 6);
+
+        let mut shapes = vec![];
+        room.flatten(&Matrix4::identity(), &mut shapes);
+        assert_eq!(shapes.len(), 6);
+        assert_eq!(Arc::strong_count(&table), 4);
+
+        // the top of the last table
+        let bb = shapes[4].bounding_box();
+        assert_relative_eq!(bb.mins.x, 5.0, epsilon = 1e-9);
+        assert_relative_eq!(bb.maxs.y, 1.1, epsilon = 1e-9);
+        assert_relative_eq!(bb.maxs.z, -4.0, epsilon = 1e-9);
+    }
+}
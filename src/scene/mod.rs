@@ -1,5 +1,12 @@
 //! Export Scene and SceneBuilder class.
+mod entry;
+mod group;
+#[allow(clippy::module_inception)]
 pub mod scene;
+mod style;
 mod visitors;
 
-pub use scene::{Scene, SceneBuilder};
+pub use entry::{SceneEntry, ShapeInfo};
+pub use group::Group;
+pub use scene::{RenderOutput, RenderPath, RenderedPath, Scene, SceneBuilder};
+pub use style::Style;
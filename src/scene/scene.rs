@@ -1,25 +1,38 @@
 //! A `Scene` is a collection of objects to render.
-use super::visitors::{CameraVisiblePathCollector, SceneOcclusionVisitor};
+use super::visitors::{CameraVisibleShapeCollector, SceneOcclusionVisitor};
+use super::{Group, SceneEntry, ShapeInfo, Style};
 use crate::common::*;
+use crate::error::{PartialRender, RenderError};
 use crate::shape::Shape;
-use crate::{camera::Camera, shape::Path};
-use approx::assert_relative_eq;
+use crate::{
+    camera::{Camera, VisibilityMode},
+    shape::Path,
+};
+use approx::relative_eq;
 use ncollide3d::bounding_volume::AABB;
 use ncollide3d::{
     partitioning::{BVH, BVT},
     query::Ray,
 };
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::sync::Arc;
 
 /// A scene is a collection of shapes in space that can be rendered.
 pub struct Scene {
-    /// bounded-volume tree for containing objects
-    bvt: BVT<Box<dyn Shape>, AABB<f64>>,
+    shapes: Vec<Box<dyn Shape>>,
+
+    /// identity of each shape, attached to its rendered paths
+    infos: Vec<Arc<ShapeInfo>>,
+
+    /// bounded-volume tree over the indices of `shapes`
+    bvt: BVT<usize, AABB<f64>>,
 }
 
 /// Convenience class for incrementally building a scene.
 #[derive(Default)]
 pub struct SceneBuilder {
-    shapes: Vec<Box<dyn Shape>>,
+    entries: Vec<SceneEntry>,
 }
 
 impl SceneBuilder {
@@ -28,9 +41,9 @@ impl SceneBuilder {
     }
 
     /// Add a single shape.
-    pub fn add<S: Shape + 'static>(mut self, shape: S) -> Self {
-        self.shapes.push(Box::new(shape));
-        self
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<S: Shape + 'static>(self, shape: S) -> Self {
+        self.add_entry(SceneEntry::new(shape))
     }
 
     /// Add many shapes.
@@ -39,18 +52,64 @@ impl SceneBuilder {
         I: IntoIterator<Item = S>,
     {
         for s in shapes.into_iter() {
-            self.shapes.push(Box::new(s));
+            self = self.add(s);
         }
         self
     }
 
+    /// Add a group of shapes.
+    pub fn add_group(self, group: Group) -> Self {
+        self.add_entry(SceneEntry::group(group))
+    }
+
+    /// Add an instance of a shared group.
+    pub fn add_instance(self, group: Arc<Group>) -> Self {
+        self.add_entry(SceneEntry::instance(group))
+    }
+
+    /// Add a single shape, drawn with `style`.
+    pub fn add_styled<S: Shape + 'static>(self, shape: S, style: Style) -> Self {
+        self.add_entry(SceneEntry::new(shape).style(style))
+    }
+
+    /// Add a shape or group, along with its attributes.
+    pub fn add_entry(mut self, entry: SceneEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
     /// Build the final scene.
     pub fn build(self) -> Scene {
-        Scene::new(self.shapes)
+        let mut shapes = vec![];
+        let mut infos = vec![];
+        for entry in self.entries {
+            entry.flatten(&mut shapes, &mut infos);
+        }
+        Scene::with_infos(shapes, infos)
     }
 }
 
-pub(crate) type RenderPath = Vec<Point2<f64>>;
+/// 2D path, in NDC-space.
+pub type RenderPath = Vec<Point2<f64>>;
+
+/// Path rendered from a shape in a scene.
+#[derive(Clone, Debug)]
+pub struct RenderedPath {
+    /// identity of the shape the path was rendered from
+    pub shape: Arc<ShapeInfo>,
+
+    pub points: RenderPath,
+}
+
+/// Paths rendered from a scene, split by visibility.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOutput {
+    /// paths visible from the camera
+    pub visible: Vec<RenderedPath>,
+
+    /// paths hidden behind other shapes, or the back of their own
+    pub hidden: Vec<RenderedPath>,
+}
 
 /// Given a RenderPath (in NDC-space), transform the path into [0, w]
 /// ✕ [0, h] coordinate space (flipping the y-axis in the processes)
@@ -64,6 +123,30 @@ pub fn format_svg_poly_data(p: &RenderPath, w: f64, h: f64) -> String {
     strs.join(" ")
 }
 
+/// Collect rendered paths into an svg group node, with a nested group
+/// for each distinct style carrying its stroke attributes.
+///
+/// Style groups appear in the order their first path does, and the
+/// paths within each keep their order.
+#[cfg(feature = "svg")]
+pub fn format_svg_paths(paths: &[RenderedPath], w: f64, h: f64) -> svg::node::element::Group {
+    let mut styles: Vec<(&Style, svg::node::element::Group)> = vec![];
+    for p in paths {
+        let polyline = svg::node::element::Polyline::new()
+            .set("points", format_svg_poly_data(&p.points, w, h));
+        match styles
+            .iter_mut()
+            .find(|(style, _)| **style == p.shape.style)
+        {
+            Some((_, g)) => svg::Node::append(g, polyline),
+            None => styles.push((&p.shape.style, p.shape.style.svg_group().add(polyline))),
+        }
+    }
+    styles
+        .into_iter()
+        .fold(svg::node::element::Group::new(), |g, (_, sg)| g.add(sg))
+}
+
 /// Encapsulate the state of the current path being built in
 /// `Scene::render_segment_adaptive`.
 #[derive(Clone, Debug)]
@@ -114,11 +197,136 @@ impl SegmentPathState {
     }
 }
 
-pub fn split_segment_adaptive(camera: &Camera, p0: &Point3<f64>, p1: &Point3<f64>) -> (Path, Path) {
+/// Paths being built along a 3d path, split into the parts that are
+/// visible and (optionally) the parts that are hidden.
+struct SplitPaths {
+    visible: SegmentPathState,
+
+    /// the hidden path being built, if hidden paths are kept
+    hidden: Option<SegmentPathState>,
+
+    /// visibility of the last point added to the current path
+    last_visible: Option<bool>,
+
+    /// the last point added to the current path
+    last_point: Option<Point2<f64>>,
+
+    finished_visible: Vec<RenderPath>,
+    finished_hidden: Vec<RenderPath>,
+}
+
+impl SplitPaths {
+    fn new(keep_hidden: bool) -> SplitPaths {
+        SplitPaths {
+            visible: SegmentPathState::Empty,
+            hidden: if keep_hidden {
+                Some(SegmentPathState::Empty)
+            } else {
+                None
+            },
+            last_visible: None,
+            last_point: None,
+            finished_visible: vec![],
+            finished_hidden: vec![],
+        }
+    }
+
+    /// Add the next point along the path.
+    fn add(&mut self, point: Point2<f64>, visible: bool) {
+        update_state(
+            &mut self.visible,
+            Some(point).filter(|_| visible),
+            &mut self.finished_visible,
+        );
+        if let Some(hidden) = self.hidden.as_mut() {
+            update_state(
+                hidden,
+                Some(point).filter(|_| !visible),
+                &mut self.finished_hidden,
+            );
+        }
+        self.last_visible = Some(visible);
+        self.last_point = Some(point);
+    }
+
+    /// Add the next sample along the path.
+    ///
+    /// Unlike exact crossings, samples don't lie where visibility
+    /// changes, so each hidden path is extended to the visible sample
+    /// on either side of it, leaving no gap between them.
+    fn add_sample(&mut self, point: Point2<f64>, visible: bool) {
+        if let Some(hidden) = self.hidden.as_mut() {
+            let shared = match self.last_visible {
+                Some(true) if !visible => self.last_point,
+                Some(false) if visible => Some(point),
+                _ => None,
+            };
+            if shared.is_some() {
+                update_state(hidden, shared, &mut self.finished_hidden);
+            }
+        }
+        self.add(point, visible);
+    }
+
+    /// Start a new segment of the path, keeping the last point added
+    /// as a vertex.
+    fn start_segment(&mut self) {
+        fn restart(state: &mut SegmentPathState) {
+            *state = match std::mem::replace(state, SegmentPathState::Empty) {
+                SegmentPathState::Continuing(path) => SegmentPathState::Started(path),
+                other => other,
+            };
+        }
+        restart(&mut self.visible);
+        if let Some(hidden) = self.hidden.as_mut() {
+            restart(hidden);
+        }
+    }
+
+    /// Finish any paths in progress, at the end of a path.
+    fn end_path(&mut self) {
+        fn finish(state: &mut SegmentPathState, finished: &mut Vec<RenderPath>) {
+            if let Some(p) = std::mem::replace(state, SegmentPathState::Empty).into_path() {
+                if p.len() > 1 {
+                    finished.push(p);
+                }
+            }
+        }
+        finish(&mut self.visible, &mut self.finished_visible);
+        if let Some(hidden) = self.hidden.as_mut() {
+            finish(hidden, &mut self.finished_hidden);
+        }
+        self.last_visible = None;
+        self.last_point = None;
+    }
+}
+
+/// Update a path being built with the next point, keeping the path
+/// it finishes, if any.
+fn update_state(
+    state: &mut SegmentPathState,
+    point: Option<Point2<f64>>,
+    finished: &mut Vec<RenderPath>,
+) {
+    let (new_state, finished_path) =
+        std::mem::replace(state, SegmentPathState::Empty).update(point);
+    if let Some(fp) = finished_path {
+        if fp.len() > 1 {
+            finished.push(fp);
+        }
+    }
+    *state = new_state;
+}
+
+pub fn split_segment_adaptive(
+    camera: &Camera,
+    p0: &Point3<f64>,
+    p1: &Point3<f64>,
+) -> Result<(Path, Path), RenderError> {
     // Project the segment, split it to the desired resolution,
     // unproject them, and perform the render.
-    let proj_p0 = camera.project_3d(&p0);
-    let proj_p1 = camera.project_3d(&p1);
+    let proj_p0 = camera.project_3d(p0);
+    let proj_p1 = camera.project_3d(p1);
 
     let dist_2d_sq = (proj_p0.xy() - proj_p1.xy()).norm_squared();
     let sres = camera.resolution();
@@ -137,23 +345,58 @@ pub fn split_segment_adaptive(camera: &Camera, p0: &Point3<f64>, p1: &Point3<f64
     let d = (segments[1] - segments[0]).normalize();
     for i in 2..segments.len() {
         let d2 = (segments[i] - segments[0]).normalize();
-        assert_relative_eq!(d.dot(&d2), 1.0);
+        let cos = d.dot(&d2);
+        if !relative_eq!(cos, 1.0, epsilon = 1e-9) {
+            return Err(RenderError::CurvedSplit(1.0 - cos));
+        }
     }
 
-    (segments, proj_segments)
+    Ok((segments, proj_segments))
+}
+
+/// Convert the output of a render, complete or partial, with `f`.
+fn map_partial<T, U, F: FnOnce(T) -> U>(
+    result: Result<T, PartialRender<T>>,
+    f: F,
+) -> Result<U, PartialRender<U>> {
+    match result {
+        Ok(output) => Ok(f(output)),
+        Err(partial) => Err(partial.map(f)),
+    }
 }
 
 impl Scene {
     pub fn new(shapes: Vec<Box<dyn Shape>>) -> Scene {
-        let shapes_and_bounds = shapes
-            .into_iter()
-            .map(|s| {
-                let bb = s.bounding_box();
-                (s, bb)
+        let infos = shapes
+            .iter()
+            .enumerate()
+            .map(|(index, s)| {
+                Arc::new(ShapeInfo {
+                    index,
+                    name: s.name(),
+                    tag: None,
+                    style: Style::new(),
+                    occluding: true,
+                    drawn: true,
+                })
             })
             .collect();
-        let bvt = BVT::new_balanced(shapes_and_bounds);
-        Scene { bvt }
+        Scene::with_infos(shapes, infos)
+    }
+
+    fn with_infos(shapes: Vec<Box<dyn Shape>>, infos: Vec<Arc<ShapeInfo>>) -> Scene {
+        let indices_and_bounds = shapes
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, s.bounding_box()))
+            .collect();
+        let bvt = BVT::new_balanced(indices_and_bounds);
+        Scene { shapes, infos, bvt }
+    }
+
+    /// Return the identity of each shape in the scene, by index.
+    pub fn shape_infos(&self) -> &[Arc<ShapeInfo>] {
+        &self.infos
     }
 
     /// Render a line segment adaptive based on the desired screen resolution.
@@ -162,41 +405,104 @@ impl Scene {
     /// paths, depending on what lies in front of it and how much it
     /// needs to be adaptively modified.
     ///
-    /// Returns an in-progress path, representing
-    /// a (non-proper) prefix of the the current segment.
+    /// Points are added to `paths`, continuing any path in progress
+    /// from the previous segment.
     fn render_segment_adaptive(
         &self,
         camera: &Camera,
         p0: &Point3<f64>,
         p1: &Point3<f64>,
-        paths: &mut Vec<RenderPath>,
-        // The Path currently being built, if one exists.
-        curr_path: Option<RenderPath>,
-    ) -> Option<RenderPath> {
-        let (points, proj_points) = split_segment_adaptive(camera, p0, p1);
+        paths: &mut SplitPaths,
+    ) -> Result<(), RenderError> {
+        let (points, proj_points) = split_segment_adaptive(camera, p0, p1)?;
         //eprintln!("Split segment ({}, {}) into {} points", p0, p1, points.len());
 
-        let (first_point, mut path_state) = match curr_path {
-            Some(p) if !p.is_empty() => (1, SegmentPathState::Started(p)),
-            _ => (0, SegmentPathState::Empty),
-        };
+        // The first point was already added as the end of the last
+        // segment.
+        let first_point = if paths.last_visible.is_some() { 1 } else { 0 };
+        paths.start_segment();
 
         for i in first_point..points.len() {
             let is_visible = self.is_point_visible(camera, &points[i], proj_points[i]);
             //eprint!("{}", if is_visible { "―" } else { " " });
-            let (new_path_state, finished_path) = path_state.update(if is_visible {
-                Some(proj_points[i].xy())
-            } else {
-                None
-            });
-            if let Some(fp) = finished_path {
-                if fp.len() > 1 {
-                    paths.push(fp);
+            paths.add_sample(proj_points[i].xy(), is_visible);
+        }
+        Ok(())
+    }
+
+    /// Render a line segment, bisecting changes in visibility along
+    /// it to find exactly where it is occluded.
+    ///
+    /// Visibility is sampled every `step` of the camera's
+    /// `VisibilityMode::Exact`, and any change between samples is
+    /// refined to within its `tolerance`.  As with
+    /// `render_segment_adaptive`, points are added to `paths`.
+    fn render_segment_exact(
+        &self,
+        camera: &Camera,
+        p0: &Point3<f64>,
+        p1: &Point3<f64>,
+        paths: &mut SplitPaths,
+    ) {
+        const MAX_BISECTIONS: usize = 64;
+        let (step, tolerance) = match camera.visibility_mode() {
+            VisibilityMode::Exact { step, tolerance } => (step, tolerance),
+            VisibilityMode::Sampled => (camera.resolution(), camera.resolution()),
+        };
+
+        // Interpolating in NDC keeps the samples evenly spaced on
+        // screen, and still on the segment in world space.
+        let (q0, q1) = (camera.project_3d(p0), camera.project_3d(p1));
+        let length = (q1.xy() - q0.xy()).norm();
+        let at = |s: f64| (q0 + (q1 - q0) * s).xy();
+        let is_visible = |s: f64| {
+            let q = q0 + (q1 - q0) * s;
+            self.is_point_visible(camera, &camera.unproject(&q), q)
+        };
+
+        // The start of this segment is the end of the last, if any.
+        let mut was_visible = match paths.last_visible {
+            Some(visible) => visible,
+            None => {
+                let visible = is_visible(0.0);
+                paths.add(at(0.0), visible);
+                visible
+            }
+        };
+        paths.start_segment();
+
+        let n = ((length / step).ceil() as usize).max(1);
+        let mut prev_s = 0.0;
+        for i in 1..=n {
+            let s = i as f64 / n as f64;
+            let visible = is_visible(s);
+            if visible != was_visible {
+                let (mut lo, mut hi) = (prev_s, s);
+                for _ in 0..MAX_BISECTIONS {
+                    if (hi - lo) * length <= tolerance {
+                        break;
+                    }
+                    let mid = 0.5 * (lo + hi);
+                    if is_visible(mid) == was_visible {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                // End one path and start the other at the crossing,
+                // only adding it if it isn't already a sample.
+                let crossing = if was_visible { lo } else { hi };
+                if crossing > prev_s {
+                    paths.add(at(crossing), was_visible);
+                }
+                if crossing < s {
+                    paths.add(at(crossing), visible);
                 }
             }
-            path_state = new_path_state;
+            paths.add(at(s), visible);
+            was_visible = visible;
+            prev_s = s;
         }
-        path_state.into_path()
     }
 
     /// Given a point that we otherwise expect to be able to render
@@ -211,7 +517,7 @@ impl Scene {
         // project the point into 2d
         let proj_point = proj_point
             .into()
-            .unwrap_or_else(|| camera.project_3d(&point));
+            .unwrap_or_else(|| camera.project_3d(point));
 
         // find the equivalent point projected behind slightly on the
         // near plane.
@@ -223,7 +529,7 @@ impl Scene {
 
         let ray = Ray::new(origin, unnorm_dir / target_toi);
 
-        let mut sov = SceneOcclusionVisitor::new(&ray, target_toi);
+        let mut sov = SceneOcclusionVisitor::new(&self.shapes, &self.infos, &ray, target_toi);
         self.bvt.visit(&mut sov);
 
         !sov.is_occluded()
@@ -231,41 +537,156 @@ impl Scene {
 
     /// Render a 3d-path onto one or more 2d paths in normalized
     /// coordinates.
-    pub fn render_path(&self, path: &Vec<Point3<f64>>, camera: &Camera) -> Vec<Vec<Point2<f64>>> {
-        let clipped_paths = camera.clip_path(path);
-        let mut all_paths = vec![];
+    pub fn render_path(
+        &self,
+        path: &Vec<Point3<f64>>,
+        camera: &Camera,
+    ) -> Result<Vec<Vec<Point2<f64>>>, RenderError> {
+        Ok(self
+            .render_split_path(path, camera, false)?
+            .finished_visible)
+    }
+
+    /// Render a 3d-path onto 2d paths, split into the visible and
+    /// (if `keep_hidden`) hidden parts.
+    fn render_split_path(
+        &self,
+        path: &Path,
+        camera: &Camera,
+        keep_hidden: bool,
+    ) -> Result<SplitPaths, RenderError> {
+        let clipped_paths = camera.clip_path(path)?;
+        let mut paths = SplitPaths::new(keep_hidden);
 
         for clipped_path in clipped_paths {
-            let mut curr_render_path = None;
             for i in 1..clipped_path.len() {
                 let prev_point = &clipped_path[i - 1];
                 let point = &clipped_path[i];
 
-                curr_render_path = self.render_segment_adaptive(
-                    camera,
-                    prev_point,
-                    point,
-                    &mut all_paths,
-                    curr_render_path,
-                );
-            }
-            if let Some(p) = curr_render_path {
-                if p.len() >= 2 {
-                    all_paths.push(p)
+                match camera.visibility_mode() {
+                    VisibilityMode::Sampled => {
+                        self.render_segment_adaptive(camera, prev_point, point, &mut paths)?
+                    }
+                    VisibilityMode::Exact { .. } => {
+                        self.render_segment_exact(camera, prev_point, point, &mut paths)
+                    }
                 }
             }
+            paths.end_path();
         }
 
-        all_paths
+        Ok(paths)
     }
 
     /// Return a collection of paths that visible from the provided
     /// camera.
-    pub fn render(&self, camera: &Camera) -> Vec<RenderPath> {
-        let mut visitor = CameraVisiblePathCollector::new(self, camera.clone());
+    ///
+    /// With the `rayon` feature, shapes are rendered in parallel. The
+    /// paths are the same, in the same order, either way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any path is too degenerate to render. Use
+    /// `try_render` to handle this instead.
+    pub fn render(&self, camera: &Camera) -> Vec<RenderedPath> {
+        self.try_render(camera)
+            .unwrap_or_else(|e| panic!("could not render scene: {}", e))
+    }
+
+    /// Return a collection of paths that visible from the provided
+    /// camera.
+    ///
+    /// Paths too degenerate to render are left out, and returned as
+    /// errors along with everything else.
+    pub fn try_render(
+        &self,
+        camera: &Camera,
+    ) -> Result<Vec<RenderedPath>, PartialRender<Vec<RenderedPath>>> {
+        map_partial(self.render_shapes(camera, false), |output| output.visible)
+    }
+
+    /// Return the paths visible from the provided camera, along with
+    /// the paths hidden behind other shapes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any path is too degenerate to render, as with
+    /// `render`.
+    pub fn render_with_hidden(&self, camera: &Camera) -> RenderOutput {
+        self.try_render_with_hidden(camera)
+            .unwrap_or_else(|e| panic!("could not render scene: {}", e))
+    }
+
+    /// Return the paths visible from the provided camera, along with
+    /// the paths hidden behind other shapes, leaving out paths too
+    /// degenerate to render as with `try_render`.
+    pub fn try_render_with_hidden(
+        &self,
+        camera: &Camera,
+    ) -> Result<RenderOutput, PartialRender<RenderOutput>> {
+        self.render_shapes(camera, true)
+    }
+
+    fn render_shapes(
+        &self,
+        camera: &Camera,
+        keep_hidden: bool,
+    ) -> Result<RenderOutput, PartialRender<RenderOutput>> {
+        let mut visitor = CameraVisibleShapeCollector::new(camera, &self.infos);
         self.bvt.visit(&mut visitor);
 
-        visitor.rendered_paths
+        #[cfg(feature = "rayon")]
+        let shapes = visitor.visible_shapes.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let shapes = visitor.visible_shapes.iter();
+
+        let rendered: Vec<_> = shapes
+            .map(|i| self.render_shape(*i, camera, keep_hidden))
+            .collect();
+        let mut output = RenderOutput::default();
+        let mut errors = vec![];
+        for (r, e) in rendered {
+            output.visible.extend(r.visible);
+            output.hidden.extend(r.hidden);
+            errors.extend(e);
+        }
+        if errors.is_empty() {
+            Ok(output)
+        } else {
+            Err(PartialRender { output, errors })
+        }
+    }
+
+    /// Render all of the paths of a shape, along with the errors from
+    /// any paths that could not be rendered.
+    fn render_shape(
+        &self,
+        index: usize,
+        camera: &Camera,
+        keep_hidden: bool,
+    ) -> (RenderOutput, Vec<(usize, RenderError)>) {
+        let attribute = |points| RenderedPath {
+            shape: self.infos[index].clone(),
+            points,
+        };
+        let mut output = RenderOutput::default();
+        let mut errors = vec![];
+        for path in self.shapes[index].paths(camera) {
+            let paths = match self.render_split_path(&path, camera, keep_hidden) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    errors.push((index, e));
+                    continue;
+                }
+            };
+            output
+                .visible
+                .extend(paths.finished_visible.into_iter().map(attribute));
+            output
+                .hidden
+                .extend(paths.finished_hidden.into_iter().map(attribute));
+        }
+        (output, errors)
     }
 
     /// Return a collection of paths that visible from the provided
@@ -273,17 +694,430 @@ impl Scene {
     ///
     /// The 2d- paths are transformed from NDC coordinates to the
     /// space [0, `dim.0`] ✕ [0, `dim.1.`], including flipping the
-    /// y-axis.
+    /// y-axis. Paths are grouped by their style, as with
+    /// `format_svg_paths`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any path is too degenerate to render, as with
+    /// `render`.
     #[cfg(feature = "svg")]
     pub fn render_to_svg(&self, camera: &Camera, dim: (f64, f64)) -> svg::node::element::Group {
-        let paths = self.render(camera);
-        let mut g = svg::node::element::Group::new();
-        for p in paths {
-            g = g.add(
-                svg::node::element::Polyline::new()
-                    .set("points", format_svg_poly_data(&p, dim.0, dim.1)),
-            );
+        self.try_render_to_svg(camera, dim)
+            .unwrap_or_else(|e| panic!("could not render scene: {}", e))
+    }
+
+    /// Return the paths visible from the provided camera as with
+    /// `render_to_svg`, leaving out paths too degenerate to render
+    /// as with `try_render`.
+    #[cfg(feature = "svg")]
+    pub fn try_render_to_svg(
+        &self,
+        camera: &Camera,
+        dim: (f64, f64),
+    ) -> Result<svg::node::element::Group, PartialRender<svg::node::element::Group>> {
+        map_partial(self.try_render(camera), |paths| {
+            format_svg_paths(&paths, dim.0, dim.1)
+        })
+    }
+
+    /// Return the paths visible from the provided camera as with
+    /// `render_to_svg`, preceded by the hidden paths in a group of
+    /// their own.
+    ///
+    /// Hidden paths are drawn in the style `hidden_style` derives
+    /// from the style of their shape, such as
+    /// `|s| s.clone().dash(&[2.0, 2.0])` to dash them in the same color
+    /// and pen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any path is too degenerate to render, as with
+    /// `render`.
+    #[cfg(feature = "svg")]
+    pub fn render_to_svg_with_hidden<F>(
+        &self,
+        camera: &Camera,
+        dim: (f64, f64),
+        hidden_style: F,
+    ) -> svg::node::element::Group
+    where
+        F: Fn(&Style) -> Style,
+    {
+        self.try_render_to_svg_with_hidden(camera, dim, hidden_style)
+            .unwrap_or_else(|e| panic!("could not render scene: {}", e))
+    }
+
+    /// Return the visible and hidden paths as with
+    /// `render_to_svg_with_hidden`, leaving out paths too degenerate
+    /// to render as with `try_render`.
+    #[cfg(feature = "svg")]
+    pub fn try_render_to_svg_with_hidden<F>(
+        &self,
+        camera: &Camera,
+        dim: (f64, f64),
+        hidden_style: F,
+    ) -> Result<svg::node::element::Group, PartialRender<svg::node::element::Group>>
+    where
+        F: Fn(&Style) -> Style,
+    {
+        let hidden_infos: Vec<_> = self
+            .infos
+            .iter()
+            .map(|info| {
+                Arc::new(ShapeInfo {
+                    style: hidden_style(&info.style),
+                    ..(**info).clone()
+                })
+            })
+            .collect();
+        map_partial(self.try_render_with_hidden(camera), |output| {
+            let hidden: Vec<_> = output
+                .hidden
+                .into_iter()
+                .map(|p| RenderedPath {
+                    shape: hidden_infos[p.shape.index].clone(),
+                    ..p
+                })
+                .collect();
+            svg::node::element::Group::new()
+                .add(format_svg_paths(&hidden, dim.0, dim.1).set("class", "hidden"))
+                .add(format_svg_paths(&output.visible, dim.0, dim.1))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{BoxOutline, Paths, Sphere};
+
+    #[test]
+    fn exact_visibility() {
+        // A sphere in front of the middle of the front face of a box.
+        let scene = SceneBuilder::new()
+            .add(BoxOutline::new(
+                Point3::new(0.0, 0.0, -2.0),
+                Vector3::new(2.0, 1.0, 0.5),
+            ))
+            .add(Sphere::new(&Point3::new(0.0, 1.0, 1.0), 0.5, None, None))
+            .build();
+        let camera = Camera::new().ortho(4.0, 3.0, 1.0, 20.0).look_at(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let points =
+            |paths: Vec<RenderedPath>| paths.into_iter().map(|p| p.points).collect::<Vec<_>>();
+        let sampled = points(scene.render(&camera));
+        let exact = points(
+            scene.render(&camera.clone().visibility(VisibilityMode::Exact {
+                step: 0.05,
+                tolerance: 1e-6,
+            })),
+        );
+        assert_eq!(sampled.len(), exact.len());
+
+        // The top edge is split by the sphere, with endings within the
+        // sampling resolution of each other.
+        let split = |paths: &[RenderPath]| {
+            let mut ends: Vec<f64> = paths
+                .iter()
+                .filter(|p| p.iter().all(|v| (v.y - 1.0 / 3.0).abs() < 1e-9))
+                .flat_map(|p| vec![p[0].x, p[p.len() - 1].x])
+                .collect();
+            ends.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            ends
+        };
+        let (sampled_ends, exact_ends) = (split(&sampled), split(&exact));
+        assert_eq!(exact_ends.len(), 4);
+        for (s, e) in sampled_ends.iter().zip(exact_ends.iter()) {
+            assert!((s - e).abs() <= camera.resolution() * 1.01);
+        }
+        // The sphere covers |x| < 0.5 * 0.99 in world space.
+        assert!((exact_ends[1] + 0.495 / 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hidden_lines() {
+        // A box behind a sphere, with only the middle of its top and
+        // bottom edges covered.
+        let scene = SceneBuilder::new()
+            .add_styled(
+                BoxOutline::new(Point3::new(0.0, 0.0, -2.0), Vector3::new(2.0, 0.25, 0.5)),
+                Style::new().color("red").pen(2),
+            )
+            .add(Sphere::new(&Point3::new(0.0, 0.0, 1.0), 0.5, None, None))
+            .build();
+        let camera = Camera::new().ortho(4.0, 3.0, 1.0, 20.0).look_at(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let output = scene.render_with_hidden(&camera);
+        let points =
+            |paths: &[RenderedPath]| paths.iter().map(|p| p.points.clone()).collect::<Vec<_>>();
+        assert_eq!(points(&output.visible), points(&scene.render(&camera)));
+
+        // The front top edge is hidden where the sphere, narrower at
+        // that height than its full width, covers it.
+        let covered = output.hidden.iter().find(|p| {
+            p.shape.index == 0 && {
+                let (a, b) = (p.points[0], p.points[p.points.len() - 1]);
+                (a.y - 0.25 / 3.0).abs() < 1e-9
+                    && (b.y - 0.25 / 3.0).abs() < 1e-9
+                    && (a.x + b.x).abs() < 1e-9
+                    && b.x > 0.1
+                    && b.x < 0.125
+            }
+        });
+
+        // It picks up where the visible parts of the edge leave off.
+        let covered = covered.unwrap();
+        for end in &[covered.points[0], covered.points[covered.points.len() - 1]] {
+            assert!(output
+                .visible
+                .iter()
+                .any(|p| p.points[0] == *end || p.points[p.points.len() - 1] == *end));
+        }
+
+        // Hidden paths are styled after their shapes.
+        #[cfg(feature = "svg")]
+        {
+            let svg = scene
+                .render_to_svg_with_hidden(&camera, (100.0, 100.0), |s| s.clone().dash(&[1.0, 1.0]))
+                .to_string();
+            assert_eq!(svg.matches("stroke-dasharray=\"1 1\"").count(), 1);
+            assert_eq!(svg.matches("stroke=\"red\"").count(), 2);
+        }
+    }
+
+    #[test]
+    fn rendered_path_identity() {
+        let unit = Vector3::new(0.5, 0.5, 0.5);
+        let pair = Group::new()
+            .add(BoxOutline::new(Point3::new(-1.0, 0.0, 0.0), unit))
+            .add(BoxOutline::new(Point3::new(1.0, 0.0, 0.0), unit));
+        let scene = SceneBuilder::new()
+            .add(BoxOutline::new(Point3::new(0.0, 2.0, 0.0), unit))
+            .add_entry(
+                SceneEntry::group(pair)
+                    .tag("pair")
+                    .style(Style::new().dash(&[2.0, 1.0]).pen(2)),
+            )
+            .build();
+        let camera = Camera::new().ortho(4.0, 4.0, 1.0, 20.0).look_at(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let infos = scene.shape_infos();
+        assert_eq!(infos.len(), 3);
+        assert_eq!(infos[2].tag.as_deref(), Some("pair"));
+        assert!(infos[0].tag.is_none());
+
+        // Every path comes from the shape it lies on.
+        let paths = scene.render(&camera);
+        assert!(!paths.is_empty());
+        for path in paths {
+            let x = path.points.iter().map(|p| p.x).sum::<f64>() / path.points.len() as f64;
+            let y = path.points.iter().map(|p| p.y).sum::<f64>() / path.points.len() as f64;
+            match path.shape.index {
+                0 => assert!(y > 0.3),
+                1 => assert!(x < -0.1 && path.shape.tag.is_some()),
+                _ => assert!(x > 0.1 && path.shape.name.starts_with("Box")),
+            }
+        }
+
+        // The styled pair gets a group of its own.
+        #[cfg(feature = "svg")]
+        {
+            let svg = scene.render_to_svg(&camera, (100.0, 100.0)).to_string();
+            assert_eq!(svg.matches("<g").count(), 3);
+            assert_eq!(svg.matches("stroke-dasharray=\"2 1\"").count(), 1);
+            assert_eq!(svg.matches("data-pen=\"2\"").count(), 1);
+        }
+    }
+
+    #[test]
+    fn occlusion_flags() {
+        // A box behind a sphere covering the middle of its front top
+        // edge, as in `hidden_lines`.
+        let scene = |sphere: fn(SceneEntry) -> SceneEntry| {
+            SceneBuilder::new()
+                .add(BoxOutline::new(
+                    Point3::new(0.0, 0.0, -2.0),
+                    Vector3::new(2.0, 0.25, 0.5),
+                ))
+                .add_entry(sphere(SceneEntry::new(Sphere::new(
+                    &Point3::new(0.0, 0.0, 1.0),
+                    0.5,
+                    Some(0.5),
+                    None,
+                ))))
+                .build()
+        };
+        let camera = Camera::new().ortho(4.0, 3.0, 1.0, 20.0).look_at(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+        let box_paths = |scene: &Scene| {
+            scene
+                .render(&camera)
+                .into_iter()
+                .filter(|p| p.shape.index == 0)
+                .count()
+        };
+
+        let normal = scene(|e| e);
+        let ghosted = scene(SceneEntry::non_occluding);
+        let occluder = scene(SceneEntry::occlude_only);
+        assert!(!ghosted.shape_infos()[1].occluding);
+        assert!(!occluder.shape_infos()[1].drawn);
+
+        // Only the occluding sphere splits the top and bottom edges.
+        assert_eq!(box_paths(&normal), box_paths(&ghosted) + 2);
+        assert_eq!(box_paths(&normal), box_paths(&occluder));
+
+        // Only the drawn sphere is rendered.
+        let sphere_paths = |scene: &Scene| {
+            scene
+                .render(&camera)
+                .into_iter()
+                .filter(|p| p.shape.index == 1)
+                .count()
+        };
+        assert!(sphere_paths(&ghosted) > 0);
+        assert_eq!(sphere_paths(&occluder), 0);
+    }
+
+    #[test]
+    fn near_plane_inside_shape() {
+        // A sphere cut in half by the near plane, with one box inside
+        // it and another behind it.
+        let inner =
+            || BoxOutline::from_extents(Point3::new(-0.3, -0.3, 7.6), Point3::new(0.3, 0.3, 8.0));
+        let behind =
+            BoxOutline::from_extents(Point3::new(-0.3, -0.3, -1.0), Point3::new(0.3, 0.3, 0.0));
+        let sphere = Sphere::new(&Point3::new(0.0, 0.0, 9.0), 2.0, None, None);
+        let camera = Camera::new().ortho(3.0, 3.0, 1.0, 20.0).look_at(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+        let points = |scene: &Scene, index: usize| {
+            scene
+                .render(&camera)
+                .into_iter()
+                .filter(|p| p.shape.index == index)
+                .map(|p| p.points)
+                .collect::<Vec<_>>()
+        };
+
+        // Occlusion rays from the near plane start inside the solid
+        // sphere, which hides both boxes.
+        let scene = SceneBuilder::new()
+            .add(inner())
+            .add(behind)
+            .add(sphere)
+            .build();
+        let alone = SceneBuilder::new().add(inner()).build();
+        assert!(!points(&alone, 0).is_empty());
+        assert!(points(&scene, 0).is_empty());
+        assert!(points(&scene, 1).is_empty());
+    }
+
+    #[test]
+    fn degenerate_paths_skipped() {
+        // Strokes that occlude nothing: one across the view, one
+        // leaving it too nearly parallel to its right side to clip,
+        // and one through opposite corners of the view.
+        struct Strokes(Paths);
+        impl Shape for Strokes {
+            fn intersect(&self, _ray: &Ray<f64>, _max_toi: f64) -> Option<f64> {
+                None
+            }
+            fn paths(&self, _camera: &Camera) -> Paths {
+                self.0.clone()
+            }
+            fn bounding_box(&self) -> AABB<f64> {
+                AABB::new(Point3::new(-3.0, -3.0, -1.0), Point3::new(3.0, 3.0, 1.0))
+            }
+        }
+
+        let scene = SceneBuilder::new()
+            .add(Strokes(vec![
+                vec![Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0)],
+                vec![
+                    Point3::new(2.0 - 1e-9, 0.0, 0.0),
+                    Point3::new(2.0 + 1e-7, 0.5, 0.0),
+                ],
+                vec![Point3::new(-3.0, -3.0, 0.0), Point3::new(3.0, 3.0, 0.0)],
+            ]))
+            .build();
+        let camera = Camera::new().ortho(2.0, 2.0, 1.0, 20.0).look_at(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let partial = scene.try_render(&camera).unwrap_err();
+        assert_eq!(partial.errors.len(), 1);
+        assert_eq!(partial.errors[0].0, 0);
+        assert_eq!(partial.errors[0].1, RenderError::MissingFrustumCrossing);
+
+        // The diagonal is drawn from corner to corner.
+        assert_eq!(partial.output.len(), 2);
+        let diagonal = &partial.output[1].points;
+        assert!((diagonal[0] - Point2::new(-1.0, -1.0)).norm() < 1e-9);
+        assert!((diagonal[diagonal.len() - 1] - Point2::new(1.0, 1.0)).norm() < 1e-9);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_matches_serial() {
+        // A grid of boxes partly hidden behind a sphere.
+        let mut builder = SceneBuilder::new();
+        for i in -2..=2 {
+            for j in -2..=2 {
+                let c = Point3::new(i as f64, 0.0, j as f64);
+                builder = builder.add(BoxOutline::new(c, Vector3::new(0.4, 0.25, 0.4)));
+            }
+        }
+        let scene = builder
+            .add(Sphere::new(
+                &Point3::new(0.0, 1.0, 0.0),
+                1.0,
+                Some(0.5),
+                Some(0.5),
+            ))
+            .build();
+        let camera = Camera::new()
+            .look_at(
+                &Point3::new(4.0, 4.0, 6.0),
+                &Point3::new(0.0, 0.0, 0.0),
+                &Vector3::new(0.0, 1.0, 0.0),
+            )
+            .perspective(std::f64::consts::FRAC_PI_3, 1.0, 1.0, 30.0)
+            .set_resolution(0.01);
+
+        let mut visitor = CameraVisibleShapeCollector::new(&camera, &scene.infos);
+        scene.bvt.visit(&mut visitor);
+        let serial: Vec<RenderedPath> = visitor
+            .visible_shapes
+            .iter()
+            .flat_map(|i| scene.render_shape(*i, &camera, false).0.visible)
+            .collect();
+
+        let parallel = scene.render(&camera);
+        assert_eq!(parallel.len(), serial.len());
+        for (p, s) in parallel.iter().zip(&serial) {
+            assert_eq!(p.shape.index, s.shape.index);
+            assert_eq!(p.points, s.points);
         }
-        g
     }
 }
@@ -0,0 +1,80 @@
+//! Stroke styles and pen assignments for rendered paths.
+
+/// How the paths of a shape are drawn: their stroke in SVG output,
+/// and the pen used to plot them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    /// stroke color, as any SVG color
+    pub color: String,
+
+    /// stroke width, in SVG user units
+    pub width: f64,
+
+    /// alternating lengths of dashes and gaps, or empty for a solid
+    /// stroke
+    pub dash: Vec<f64>,
+
+    /// plotter pen number
+    pub pen: u32,
+}
+
+impl Style {
+    /// Create a solid, black, one unit wide style for pen 1.
+    pub fn new() -> Style {
+        Style {
+            color: "black".to_string(),
+            width: 1.0,
+            dash: vec![],
+            pen: 1,
+        }
+    }
+
+    /// Return a modified version of the style with a stroke `color`.
+    pub fn color<C: Into<String>>(self, color: C) -> Style {
+        Style {
+            color: color.into(),
+            ..self
+        }
+    }
+
+    /// Return a modified version of the style with a stroke `width`.
+    pub fn width(self, width: f64) -> Style {
+        Style { width, ..self }
+    }
+
+    /// Return a modified version of the style with a dash pattern of
+    /// alternating dash and gap lengths.
+    pub fn dash(self, dash: &[f64]) -> Style {
+        Style {
+            dash: dash.to_vec(),
+            ..self
+        }
+    }
+
+    /// Return a modified version of the style, plotted with `pen`.
+    pub fn pen(self, pen: u32) -> Style {
+        Style { pen, ..self }
+    }
+
+    /// Return a group with the stroke attributes of the style, for
+    /// paths drawn with it.
+    #[cfg(feature = "svg")]
+    pub fn svg_group(&self) -> svg::node::element::Group {
+        let mut g = svg::node::element::Group::new()
+            .set("fill", "none")
+            .set("stroke", self.color.as_str())
+            .set("stroke-width", self.width)
+            .set("data-pen", self.pen);
+        if !self.dash.is_empty() {
+            let dash: Vec<String> = self.dash.iter().map(|d| d.to_string()).collect();
+            g = g.set("stroke-dasharray", dash.join(" "));
+        }
+        g
+    }
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style::new()
+    }
+}
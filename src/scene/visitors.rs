@@ -1,40 +1,43 @@
 //! Define visitors for use when rendering scenes.
-use super::{scene::RenderPath, Scene};
+use super::ShapeInfo;
 use crate::{camera::Camera, common::*, shape::Shape};
 use na::Isometry;
 use ncollide3d::{
     partitioning::{VisitStatus, Visitor},
     query::{Ray, RayCast},
 };
+use std::sync::Arc;
 
-/// Visitor for rendering paths within view of the camera
-pub struct CameraVisiblePathCollector<'a> {
-    scene: &'a Scene,
-
+/// Visitor for collecting the shapes within view of the camera.
+///
+/// Shapes that are not drawn are skipped.
+pub struct CameraVisibleShapeCollector<'a> {
     /// camera space to render from
-    camera: Camera,
+    camera: &'a Camera,
+
+    /// identity of the shapes, indexed by the tree being visited
+    infos: &'a [Arc<ShapeInfo>],
 
-    /// Final paths rendered by this visitor
-    pub rendered_paths: Vec<RenderPath>,
+    /// indices of the visible shapes, in the order visited
+    pub visible_shapes: Vec<usize>,
 }
 
-impl<'a> CameraVisiblePathCollector<'a> {
-    pub fn new(scene: &'a Scene, camera: Camera) -> Self {
-        CameraVisiblePathCollector {
+impl<'a> CameraVisibleShapeCollector<'a> {
+    pub fn new(camera: &'a Camera, infos: &'a [Arc<ShapeInfo>]) -> Self {
+        CameraVisibleShapeCollector {
             camera,
-            scene,
-            rendered_paths: vec![],
+            infos,
+            visible_shapes: vec![],
         }
     }
 }
 
-impl<'a> Visitor<Box<dyn Shape>, AABB<f64>> for CameraVisiblePathCollector<'a> {
-    fn visit(&mut self, bv: &AABB<f64>, data: Option<&Box<dyn Shape>>) -> VisitStatus {
+impl<'a> Visitor<usize, AABB<f64>> for CameraVisibleShapeCollector<'a> {
+    fn visit(&mut self, bv: &AABB<f64>, data: Option<&usize>) -> VisitStatus {
         if self.camera.is_aabb_visible(bv) {
-            if let Some(shape) = data {
-                for path in shape.paths(&self.camera) {
-                    self.rendered_paths
-                        .extend(self.scene.render_path(&path, &self.camera));
+            if let Some(i) = data {
+                if self.infos[*i].drawn {
+                    self.visible_shapes.push(*i);
                 }
             }
             VisitStatus::Continue
@@ -46,8 +49,15 @@ impl<'a> Visitor<Box<dyn Shape>, AABB<f64>> for CameraVisiblePathCollector<'a> {
 
 /// Visitor for determining point occlusion.
 ///
-/// Yields true if the point is *occluded* by another object.
+/// Yields true if the point is *occluded* by another object. Shapes
+/// that are not occluding are ignored.
 pub struct SceneOcclusionVisitor<'b> {
+    /// shapes of the scene, indexed by the tree being visited
+    shapes: &'b [Box<dyn Shape>],
+
+    /// identity of the shapes, by the same index
+    infos: &'b [Arc<ShapeInfo>],
+
     /// Ray to be tested.
     ray: &'b Ray<f64>,
 
@@ -60,8 +70,15 @@ pub struct SceneOcclusionVisitor<'b> {
 
 impl<'b> SceneOcclusionVisitor<'b> {
     /// Creates a new `RayIntersectionCostFnVisitor`.
-    pub fn new(ray: &'b Ray<f64>, target_toi: f64) -> SceneOcclusionVisitor<'b> {
+    pub fn new(
+        shapes: &'b [Box<dyn Shape>],
+        infos: &'b [Arc<ShapeInfo>],
+        ray: &'b Ray<f64>,
+        target_toi: f64,
+    ) -> SceneOcclusionVisitor<'b> {
         SceneOcclusionVisitor {
+            shapes,
+            infos,
             ray,
             target_toi,
             is_occluded: false,
@@ -74,8 +91,8 @@ impl<'b> SceneOcclusionVisitor<'b> {
     }
 }
 
-impl<'b> Visitor<Box<dyn Shape>, AABB<f64>> for SceneOcclusionVisitor<'b> {
-    fn visit(&mut self, bv: &AABB<f64>, data: Option<&Box<dyn Shape>>) -> VisitStatus {
+impl<'b> Visitor<usize, AABB<f64>> for SceneOcclusionVisitor<'b> {
+    fn visit(&mut self, bv: &AABB<f64>, data: Option<&usize>) -> VisitStatus {
         if bv
             .toi_with_ray(&Isometry::identity(), self.ray, self.target_toi, true)
             .is_none()
@@ -84,8 +101,8 @@ impl<'b> Visitor<Box<dyn Shape>, AABB<f64>> for SceneOcclusionVisitor<'b> {
         }
 
         // If the node has data in it, check against
-        if let Some(shape) = data {
-            if let Some(result) = shape.intersect(self.ray, self.target_toi) {
+        if let Some(i) = data.filter(|i| self.infos[**i].occluding) {
+            if let Some(result) = self.shapes[*i].intersect(self.ray, self.target_toi) {
                 if result < self.target_toi - 1e-5 {
                     self.is_occluded = true;
                     return VisitStatus::ExitEarly;
@@ -35,6 +35,19 @@ impl Shape for BoxOutline {
             .toi_with_ray(&Isometry3::identity(), ray, max_toi, true)
     }
 
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.aabb
+            .toi_with_ray(&Isometry3::identity(), ray, max_toi, false)
+    }
+
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        (point - self.pos)
+            .abs()
+            .iter()
+            .zip(self.half_extents.iter())
+            .all(|(d, h)| *d <= h + eps)
+    }
+
     fn paths(&self, _camera: &Camera) -> Vec<Path> {
         let mut corners = Vec::with_capacity(8);
         for i in &[-1.0, 1.0] {
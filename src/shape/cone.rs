@@ -0,0 +1,323 @@
+//! Cones and truncated cones with rings and slant lines.
+use crate::common::*;
+use crate::shape::{
+    assert_spacing, disc_aabb, even_angles, nearest_hit, ring_path, rotation_from_y, Camera, Paths,
+    Shape, OCCLUSION_SHRINK,
+};
+use ncollide3d::bounding_volume::BoundingVolume;
+
+/// Cone, or truncated cone, oriented around the y-axis of its
+/// transform.
+///
+/// The base of the cone lies at the local origin, and the apex (or
+/// the top of the truncated cone) at `height` along the local
+/// y-axis. By default, only the rim circles are drawn. Stacked rings
+/// and slant lines can be added with `ring_spacing` and
+/// `slant_angle`.
+#[derive(Clone)]
+pub struct Cone {
+    /// local-to-world transform, with the center of the base at the
+    /// local origin
+    transform: Isometry3<f64>,
+
+    base_radius: f64,
+
+    /// radius at the top of the cone, zero for a cone with an apex
+    top_radius: f64,
+
+    height: f64,
+
+    /// whether the ends of the cone are closed for occlusion
+    caps: bool,
+
+    /// whether to draw the circles at the ends of the cone
+    rims: bool,
+
+    /// distance between rings stacked along the axis
+    ring_spacing: Option<f64>,
+
+    /// angle spacing for slant lines
+    slant_angle: Option<f64>,
+}
+
+impl Cone {
+    /// Create a capped cone with its base centered at `base` and its
+    /// apex `height` above it along the y-axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `height` is not positive and finite.
+    pub fn new(base: &Point3<f64>, radius: f64, height: f64) -> Cone {
+        Cone::truncated(base, radius, 0.0, height)
+    }
+
+    /// Create a capped, truncated cone with its base centered at
+    /// `base` and its top `height` above it along the y-axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `height` is not positive and finite.
+    pub fn truncated(base: &Point3<f64>, base_radius: f64, top_radius: f64, height: f64) -> Cone {
+        let transform = na::Isometry3::translation(base.x, base.y, base.z);
+        Cone::from_isometry(&transform, base_radius, top_radius, height)
+    }
+
+    /// Create a capped, truncated cone with its base centered at the
+    /// translation of `transform`, and its axis along the rotated
+    /// y-axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `height` is not positive and finite.
+    pub fn from_isometry(
+        transform: &Isometry3<f64>,
+        base_radius: f64,
+        top_radius: f64,
+        height: f64,
+    ) -> Cone {
+        assert_spacing(height, "cone height");
+        Cone {
+            transform: *transform,
+            base_radius,
+            top_radius,
+            height,
+            caps: true,
+            rims: true,
+            ring_spacing: None,
+            slant_angle: None,
+        }
+    }
+
+    /// Create a capped, truncated cone whose axis runs from `base` to
+    /// `top`. Use a `top_radius` of zero for a cone with its apex at
+    /// `top`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` and `top` coincide.
+    pub fn from_endpoints(
+        base: &Point3<f64>,
+        top: &Point3<f64>,
+        base_radius: f64,
+        top_radius: f64,
+    ) -> Cone {
+        let axis = top - base;
+        let transform = Isometry3::from_parts(base.coords.into(), rotation_from_y(&axis));
+        Cone::from_isometry(&transform, base_radius, top_radius, axis.norm())
+    }
+
+    /// Return a modified version of the cone with or without caps.
+    ///
+    /// Caps only affect occlusion; an uncapped cone is an open shell
+    /// that can be seen into.
+    pub fn caps(self, caps: bool) -> Cone {
+        Cone { caps, ..self }
+    }
+
+    /// Return a modified version of the cone with or without rim
+    /// circles.
+    pub fn rims(self, rims: bool) -> Cone {
+        Cone { rims, ..self }
+    }
+
+    /// Return a modified version of the cone with rings stacked
+    /// `spacing` apart along its axis, starting from the base.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `spacing` is not positive and finite.
+    pub fn ring_spacing(self, spacing: Option<f64>) -> Cone {
+        if let Some(spacing) = spacing {
+            assert_spacing(spacing, "ring spacing");
+        }
+        Cone {
+            ring_spacing: spacing,
+            ..self
+        }
+    }
+
+    /// Return a modified version of the cone with slant lines `angle`
+    /// radians apart around its axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `angle` is not positive and finite.
+    pub fn slant_angle(self, angle: Option<f64>) -> Cone {
+        if let Some(angle) = angle {
+            assert_spacing(angle, "slant angle");
+        }
+        Cone {
+            slant_angle: angle,
+            ..self
+        }
+    }
+
+    /// Return the radius of the cone at local height `y`.
+    fn radius_at(&self, y: f64) -> f64 {
+        self.base_radius + (self.top_radius - self.base_radius) * y / self.height
+    }
+
+    /// Return the time of impact of a ray against the cone, in the
+    /// local space of the cone, shrunk radially by `OCCLUSION_SHRINK`.
+    ///
+    /// The caps stay at their true heights, since shrinking along the
+    /// axis would leave the ends of a tall cone hiding nothing.
+    fn local_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        let (o, d) = (ray.origin, ray.dir);
+        let mut hits = vec![];
+
+        // slanted side of the cone, x² + z² = r(y)²
+        let k = (self.top_radius - self.base_radius) / self.height * OCCLUSION_SHRINK;
+        let r_o = self.radius_at(o.y) * OCCLUSION_SHRINK;
+        let a = d.x * d.x + d.z * d.z - k * k * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z - k * d.y * r_o);
+        let c = o.x * o.x + o.z * o.z - r_o * r_o;
+        let roots = if a.abs() < 1e-12 {
+            if b != 0.0 {
+                vec![-c / b]
+            } else {
+                vec![]
+            }
+        } else {
+            let disc = b * b - 4.0 * a * c;
+            if disc >= 0.0 {
+                let sq = disc.sqrt();
+                vec![(-b - sq) / (2.0 * a), (-b + sq) / (2.0 * a)]
+            } else {
+                vec![]
+            }
+        };
+        for t in roots {
+            let y = o.y + t * d.y;
+            if y >= 0.0 && y <= self.height {
+                hits.push(t);
+            }
+        }
+
+        // caps at either end
+        if self.caps && d.y != 0.0 {
+            for (y, r) in &[(0.0, self.base_radius), (self.height, self.top_radius)] {
+                let t = (y - o.y) / d.y;
+                let (x, z) = (o.x + t * d.x, o.z + t * d.z);
+                let r = r * OCCLUSION_SHRINK;
+                if x * x + z * z <= r * r {
+                    hits.push(t);
+                }
+            }
+        }
+        nearest_hit(hits, max_toi)
+    }
+}
+
+impl Shape for Cone {
+    fn name(&self) -> String {
+        "Cone".to_string()
+    }
+
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.local_intersect(&ray.inverse_transform_by(&self.transform), max_toi)
+    }
+
+    /// Uncapped cones still contain the points between their ends.
+    ///
+    /// `eps` is measured along the axis and radially, which only
+    /// approximates the distance to the slanted side.
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        let p = self.transform.inverse_transform_point(point);
+        let y = p.y.clamp(0.0, self.height);
+        p.y >= -eps && p.y <= self.height + eps && p.x.hypot(p.z) <= self.radius_at(y) + eps
+    }
+
+    fn paths(&self, _camera: &Camera) -> Paths {
+        let mut paths = vec![];
+        if self.rims {
+            paths.push(ring_path(&self.transform, 0.0, self.base_radius));
+            if self.top_radius > 0.0 {
+                paths.push(ring_path(&self.transform, self.height, self.top_radius));
+            }
+        }
+        if let Some(spacing) = self.ring_spacing {
+            let mut y = spacing;
+            while y < self.height - spacing * 1e-3 {
+                paths.push(ring_path(&self.transform, y, self.radius_at(y)));
+                y += spacing;
+            }
+        }
+        if let Some(angle) = self.slant_angle {
+            for theta in even_angles(angle) {
+                let (s, c) = theta.sin_cos();
+                paths.push(vec![
+                    self.transform * Point3::new(self.base_radius * s, 0.0, self.base_radius * c),
+                    self.transform
+                        * Point3::new(self.top_radius * s, self.height, self.top_radius * c),
+                ]);
+            }
+        }
+        paths
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        let axis = self.transform * Vector3::y();
+        let base: Point3<f64> = self.transform.translation.vector.into();
+        let bottom = disc_aabb(&base, &axis, self.base_radius);
+        let top = disc_aabb(&(base + axis * self.height), &axis, self.top_radius);
+        bottom.merged(&top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn cone_intersection() {
+        let cone = Cone::new(&Point3::new(0.0, 0.0, 0.0), 1.0, 2.0);
+
+        // halfway up the side, where the radius is 0.5
+        let ray = Ray::new(Point3::new(5.0, 1.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert_relative_eq!(cone.intersect(&ray, 10.0).unwrap(), 4.505, epsilon = 1e-9);
+
+        // through the base
+        let ray = Ray::new(Point3::new(0.0, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(cone.intersect(&ray, 10.0).unwrap(), 5.0, epsilon = 1e-9);
+
+        // past the apex
+        let ray = Ray::new(Point3::new(5.0, 2.5, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert!(cone.intersect(&ray, 10.0).is_none());
+
+        // a truncated cone with the top open
+        let frustum = Cone::truncated(&Point3::new(0.0, 0.0, 0.0), 2.0, 1.0, 1.0).caps(false);
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(frustum.intersect(&ray, 10.0).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "slant angle must be positive")]
+    fn zero_slant_angle() {
+        Cone::new(&Point3::origin(), 1.0, 2.0).slant_angle(Some(0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "cone height must be positive")]
+    fn coincident_endpoints() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+        Cone::from_endpoints(&p, &p, 1.0, 0.5);
+    }
+
+    #[test]
+    fn tall_frustum_occludes_to_its_ends() {
+        let frustum = Cone::truncated(&Point3::origin(), 2.0, 1.0, 100.0);
+
+        // through the side near the base, and through the base
+        let ray = Ray::new(Point3::new(5.0, 0.3, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        let r = (2.0 - 0.003) * OCCLUSION_SHRINK;
+        assert_relative_eq!(
+            frustum.intersect(&ray, 10.0).unwrap(),
+            5.0 - r,
+            epsilon = 1e-9
+        );
+        let ray = Ray::new(Point3::new(0.5, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(frustum.intersect(&ray, 10.0).unwrap(), 5.0, epsilon = 1e-9);
+    }
+}
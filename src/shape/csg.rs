@@ -0,0 +1,424 @@
+//! Constructive solid geometry: unions, intersections and
+//! differences of shapes.
+use crate::common::*;
+use crate::shape::{assert_spacing, Camera, Path, Paths, Shape};
+use crate::util::ray_aabb_interval;
+use ncollide3d::bounding_volume::BoundingVolume;
+
+/// Maximum number of surface crossings followed along a single ray.
+const MAX_CROSSINGS: usize = 64;
+
+/// Number of bisection steps used to find where a path leaves the
+/// resulting surface.
+const BOUNDARY_ITERATIONS: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    /// Return whether a point is inside the result, given whether it
+    /// is inside each operand.
+    fn apply(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            Operation::Union => in_a || in_b,
+            Operation::Intersection => in_a && in_b,
+            Operation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// Shared implementation of the boolean shapes.
+struct Boolean {
+    op: Operation,
+    a: Box<dyn Shape>,
+    b: Box<dyn Shape>,
+
+    /// maximum distance between samples when filtering paths
+    step: f64,
+}
+
+impl Boolean {
+    fn new(op: Operation, a: Box<dyn Shape>, b: Box<dyn Shape>) -> Boolean {
+        let bb = a.bounding_box().merged(&b.bounding_box());
+        let step = (bb.maxs - bb.mins).norm() / 200.0;
+        assert_spacing(step, "CSG step");
+        Boolean { op, a, b, step }
+    }
+
+    /// Return whether a point of a path on operand `a` (or `b`, if
+    /// `on_a` is false) lies on the surface of the result.
+    ///
+    /// Points on the surface of the other operand are given the
+    /// benefit of the doubt, so that coincident edges are kept.
+    fn keeps(&self, on_a: bool, p: &Point3<f64>) -> bool {
+        let eps = self.step * 1e-3;
+        let (other, other_is_b) = if on_a {
+            (&self.b, true)
+        } else {
+            (&self.a, false)
+        };
+        match self.op {
+            Operation::Union => !other.contains(p, -eps),
+            Operation::Intersection => other.contains(p, eps),
+            Operation::Difference if other_is_b => !other.contains(p, -eps),
+            Operation::Difference => other.contains(p, eps),
+        }
+    }
+
+    /// Split a path into the pieces lying on the surface of the
+    /// result.
+    fn filter_path(&self, on_a: bool, path: &[Point3<f64>], out: &mut Paths) {
+        let mut run: Path = vec![];
+        let mut prev: Option<(Point3<f64>, bool)> = None;
+        for (p0, p1) in path.iter().zip(path.iter().skip(1)) {
+            let n = (((p1 - p0).norm() / self.step).ceil() as usize).max(1);
+            let first = if prev.is_none() { 0 } else { 1 };
+            for i in first..=n {
+                let p = p0 + (p1 - p0) * (i as f64 / n as f64);
+                let keep = self.keeps(on_a, &p);
+                if let Some((q, kept)) = prev {
+                    if kept != keep {
+                        // find where the path crosses the other surface
+                        let (mut lo, mut hi) = (q, p);
+                        for _ in 0..BOUNDARY_ITERATIONS {
+                            let mid = na::center(&lo, &hi);
+                            if self.keeps(on_a, &mid) == kept {
+                                lo = mid;
+                            } else {
+                                hi = mid;
+                            }
+                        }
+                        if kept {
+                            run.push(lo);
+                            if run.len() > 1 {
+                                out.push(std::mem::take(&mut run));
+                            } else {
+                                run.clear();
+                            }
+                        } else {
+                            run.push(hi);
+                        }
+                    }
+                }
+                if keep {
+                    run.push(p);
+                }
+                prev = Some((p, keep));
+            }
+        }
+        if run.len() > 1 {
+            out.push(run);
+        }
+    }
+
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        // Follow the ray from one operand surface to the next,
+        // tracking which operands it is inside of, until the result
+        // changes.
+        let speed = ray.dir.norm();
+        if speed == 0.0 {
+            return None;
+        }
+        let nudge = self.step * 1e-6 / speed;
+
+        // Start from outside both operands, even if the ray itself
+        // starts inside them, so that whether it is inside agrees with
+        // the surfaces it crosses.
+        let bb = self.a.bounding_box().merged(&self.b.bounding_box());
+        let mut t = match ray_aabb_interval(&bb, ray) {
+            Some((t_enter, _)) if t_enter < 0.0 => t_enter - nudge,
+            _ => 0.0,
+        };
+        let (mut in_a, mut in_b) = (false, false);
+        let mut inside = None;
+
+        for _ in 0..MAX_CROSSINGS {
+            let r = Ray::new(ray.point_at(t), ray.dir);
+            let remaining = max_toi - t;
+            let hit_a = self.a.surface_intersect(&r, remaining);
+            let hit_b = self.b.surface_intersect(&r, remaining);
+            let dt = match (hit_a, hit_b) {
+                (None, None) => return None,
+                (Some(ta), Some(tb)) => ta.min(tb),
+                (Some(ta), None) => ta,
+                (None, Some(tb)) => tb,
+            };
+            // Note whether the result starts out inside, once the
+            // crossings before the start of the ray are accounted for.
+            if inside.is_none() && t + dt >= 0.0 {
+                inside = Some(self.op.apply(in_a, in_b));
+            }
+            // Both surfaces may be crossed at once.
+            in_a ^= hit_a.is_some_and(|ta| ta <= dt + nudge);
+            in_b ^= hit_b.is_some_and(|tb| tb <= dt + nudge);
+            t += dt;
+            if let Some(inside) = inside {
+                if self.op.apply(in_a, in_b) != inside {
+                    if inside {
+                        return Some(t);
+                    }
+                    if let Some(t) = self.entry_boundary(ray, t) {
+                        return (t <= max_toi).then_some(t);
+                    }
+                }
+            }
+            t += nudge;
+            if t > max_toi {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Return where a ray entering the result at `t` actually reaches
+    /// its interior.
+    ///
+    /// Shapes shrink their surfaces slightly for occlusion, so that
+    /// their own paths are seen from outside. Where the result is
+    /// entered by leaving an operand, as through the carved out part
+    /// of a `Difference`, or where the surfaces of the operands meet,
+    /// the shrunk surfaces don't match the result, so the crossing is
+    /// pushed on to where `contains` says the result begins. Return
+    /// `None` if it doesn't begin within a few `step`s, as when the ray
+    /// just passes between the surfaces.
+    fn entry_boundary(&self, ray: &Ray<f64>, t: f64) -> Option<f64> {
+        if self.contains(&ray.point_at(t), 0.0) {
+            return Some(t);
+        }
+        let speed = ray.dir.norm();
+        let mut step = self.step * 1e-3 / speed;
+        let mut lo = t;
+        while !self.contains(&ray.point_at(lo + step), 0.0) {
+            lo += step;
+            step *= 2.0;
+            if step * speed > self.step * 4.0 {
+                return None;
+            }
+        }
+        let mut hi = lo + step;
+        for _ in 0..BOUNDARY_ITERATIONS {
+            let mid = 0.5 * (lo + hi);
+            if self.contains(&ray.point_at(mid), 0.0) {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Some(hi)
+    }
+
+    fn paths(&self, camera: &Camera) -> Paths {
+        let mut paths = vec![];
+        for path in self.a.paths(camera) {
+            self.filter_path(true, &path, &mut paths);
+        }
+        for path in self.b.paths(camera) {
+            self.filter_path(false, &path, &mut paths);
+        }
+        paths
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        let (bb_a, bb_b) = (self.a.bounding_box(), self.b.bounding_box());
+        match self.op {
+            Operation::Union => bb_a.merged(&bb_b),
+            Operation::Intersection => {
+                let mins = bb_a.mins.sup(&bb_b.mins);
+                // keep the box valid even if the operands are disjoint
+                let maxs = bb_a.maxs.inf(&bb_b.maxs).sup(&mins);
+                AABB::new(mins, maxs)
+            }
+            Operation::Difference => bb_a,
+        }
+    }
+
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        match self.op {
+            Operation::Union => self.a.contains(point, eps) || self.b.contains(point, eps),
+            Operation::Intersection => self.a.contains(point, eps) && self.b.contains(point, eps),
+            Operation::Difference => self.a.contains(point, eps) && !self.b.contains(point, -eps),
+        }
+    }
+}
+
+/// Implement the public boolean shapes in terms of `Boolean`.
+macro_rules! boolean_shape {
+    ($name:ident, $op:expr, $doc:expr) => {
+        #[doc = $doc]
+        ///
+        /// The operands should be closed shapes, since rays and paths
+        /// are classified with `Shape::contains`. Paths are sampled
+        /// every `step` to decide which parts to keep.
+        pub struct $name(Boolean);
+
+        impl $name {
+            /// # Panics
+            ///
+            /// Panics if the operands have an empty or unbounded
+            /// bounding box, since the default `step` is a fraction of
+            /// its size.
+            pub fn new<A: Shape + 'static, B: Shape + 'static>(a: A, b: B) -> $name {
+                $name(Boolean::new($op, Box::new(a), Box::new(b)))
+            }
+
+            /// Return a modified version of the shape, sampling paths
+            /// every `step` when filtering them.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `step` is not positive and finite.
+            pub fn step(self, step: f64) -> $name {
+                assert_spacing(step, "CSG step");
+                $name(Boolean { step, ..self.0 })
+            }
+        }
+
+        impl Shape for $name {
+            fn name(&self) -> String {
+                format!(
+                    "{}({}, {})",
+                    stringify!($name),
+                    self.0.a.name(),
+                    self.0.b.name()
+                )
+            }
+
+            fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+                self.0.intersect(ray, max_toi)
+            }
+
+            fn paths(&self, camera: &Camera) -> Paths {
+                self.0.paths(camera)
+            }
+
+            fn bounding_box(&self) -> AABB<f64> {
+                self.0.bounding_box()
+            }
+
+            fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+                self.0.contains(point, eps)
+            }
+        }
+    };
+}
+
+boolean_shape!(
+    Union,
+    Operation::Union,
+    "Shape covering everything in either of two shapes."
+);
+boolean_shape!(
+    Intersection,
+    Operation::Intersection,
+    "Shape covering only what lies in both of two shapes."
+);
+boolean_shape!(
+    Difference,
+    Operation::Difference,
+    "Shape covering what lies in the first shape, with the second \
+     carved out of it."
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{BoxOutline, Cylinder, Sphere};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn sphere_minus_box() {
+        // Carve the +x half out of a unit sphere.
+        let sphere = Sphere::new(&Point3::origin(), 1.0, Some(0.5), Some(0.5));
+        let carve =
+            BoxOutline::from_extents(Point3::new(0.0, -2.0, -2.0), Point3::new(2.0, 2.0, 2.0));
+        let shape = Difference::new(sphere, carve);
+
+        assert!(shape.contains(&Point3::new(-0.5, 0.0, 0.0), 0.0));
+        assert!(!shape.contains(&Point3::new(0.5, 0.0, 0.0), 0.0));
+
+        // through the flat face of the hemisphere
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert_relative_eq!(shape.intersect(&ray, 10.0).unwrap(), 5.0, epsilon = 1e-6);
+
+        // into the curved side
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(shape.intersect(&ray, 10.0).unwrap(), 4.01, epsilon = 1e-6);
+
+        // only through the carved out region
+        let ray = Ray::new(Point3::new(0.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(shape.intersect(&ray, 10.0).is_none());
+
+        // Paths are cut at the face of the box.
+        let paths = shape.paths(&Camera::new());
+        assert!(!paths.is_empty());
+        for p in paths.iter().flatten() {
+            assert!(p.x <= 1e-3);
+        }
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let a = Sphere::new(&Point3::new(-0.5, 0.0, 0.0), 1.0, Some(0.5), Some(0.5));
+        let b = Sphere::new(&Point3::new(0.5, 0.0, 0.0), 1.0, Some(0.5), Some(0.5));
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+
+        let union = Union::new(a.clone(), b.clone());
+        assert_relative_eq!(union.intersect(&ray, 10.0).unwrap(), 3.51, epsilon = 1e-6);
+        assert!(union.contains(&Point3::new(1.2, 0.0, 0.0), 0.0));
+
+        let both = Intersection::new(a, b);
+        assert_relative_eq!(both.intersect(&ray, 10.0).unwrap(), 4.51, epsilon = 1e-6);
+
+        // Starting just inside the first sphere, but outside the
+        // slightly smaller surface used for occlusion, the ray enters
+        // it before the second.
+        let ray = Ray::new(Point3::new(-1.495, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(both.intersect(&ray, 10.0).unwrap(), 1.005, epsilon = 1e-6);
+        let bb = both.bounding_box();
+        assert_relative_eq!(bb.maxs.x, 0.5);
+        assert_relative_eq!(bb.mins.x, -0.5);
+    }
+
+    #[test]
+    fn drilled_plate() {
+        // The drill's ends meet the faces of the plate.
+        let plate = Difference::new(
+            BoxOutline::from_extents(Point3::new(-1.0, -1.0, -0.5), Point3::new(1.0, 1.0, 0.5)),
+            Cylinder::from_endpoints(
+                &Point3::new(0.0, 0.0, -0.5),
+                &Point3::new(0.0, 0.0, 0.5),
+                0.5,
+            ),
+        );
+
+        // straight through the hole
+        let ray = Ray::new(Point3::new(0.2, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(plate.intersect(&ray, 10.0).is_none());
+
+        // onto the wall of the hole, where the drill ends
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.5, 0.0, -5.0));
+        assert_relative_eq!(plate.intersect(&ray, 10.0).unwrap(), 1.0, epsilon = 1e-6);
+
+        // onto the face of the plate
+        let ray = Ray::new(Point3::new(0.8, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_relative_eq!(plate.intersect(&ray, 10.0).unwrap(), 4.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "CSG step must be positive")]
+    fn zero_step() {
+        let sphere = || Sphere::new(&Point3::origin(), 1.0, None, None);
+        Union::new(sphere(), sphere()).step(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "CSG step must be positive")]
+    fn degenerate_bounds() {
+        let sphere = || Sphere::new(&Point3::origin(), 0.0, None, None);
+        Union::new(sphere(), sphere());
+    }
+}
@@ -0,0 +1,265 @@
+//! Cylinder with rim circles and generator lines.
+use crate::common::*;
+use crate::shape::{
+    assert_spacing, disc_aabb, even_angles, nearest_hit, ring_path, rotation_from_y, Camera, Paths,
+    Shape, OCCLUSION_SHRINK,
+};
+use ncollide3d::bounding_volume::BoundingVolume;
+
+/// Cylinder, optionally capped, oriented around the y-axis of its
+/// transform.
+///
+/// By default, only the two rim circles are drawn. Stacked rings and
+/// generator lines (running the length of the cylinder) can be added
+/// with `ring_spacing` and `generator_angle`.
+#[derive(Clone)]
+pub struct Cylinder {
+    /// local-to-world transform, with the cylinder centered at the
+    /// local origin
+    transform: Isometry3<f64>,
+
+    radius: f64,
+    half_height: f64,
+
+    /// whether the ends of the cylinder are closed for occlusion
+    caps: bool,
+
+    /// whether to draw the circles at either end of the cylinder
+    rims: bool,
+
+    /// distance between rings stacked along the axis
+    ring_spacing: Option<f64>,
+
+    /// angle spacing for generator lines
+    generator_angle: Option<f64>,
+}
+
+impl Cylinder {
+    /// Create a capped cylinder centered at `pos`, with its axis
+    /// along the y-axis.
+    pub fn new(pos: &Point3<f64>, radius: f64, half_height: f64) -> Cylinder {
+        let transform = na::Isometry3::translation(pos.x, pos.y, pos.z);
+        Cylinder::from_isometry(&transform, radius, half_height)
+    }
+
+    /// Create a capped cylinder centered at the translation of
+    /// `transform`, with its axis along the rotated y-axis.
+    pub fn from_isometry(transform: &Isometry3<f64>, radius: f64, half_height: f64) -> Cylinder {
+        Cylinder {
+            transform: *transform,
+            radius,
+            half_height,
+            caps: true,
+            rims: true,
+            ring_spacing: None,
+            generator_angle: None,
+        }
+    }
+
+    /// Create a capped cylinder whose axis runs from `p0` to `p1`.
+    pub fn from_endpoints(p0: &Point3<f64>, p1: &Point3<f64>, radius: f64) -> Cylinder {
+        let axis = p1 - p0;
+        let center = na::center(p0, p1);
+        let transform = Isometry3::from_parts(center.coords.into(), rotation_from_y(&axis));
+        Cylinder::from_isometry(&transform, radius, axis.norm() * 0.5)
+    }
+
+    /// Return a modified version of the cylinder with or without
+    /// caps.
+    ///
+    /// Caps only affect occlusion; an uncapped cylinder is an open
+    /// tube that can be seen into.
+    pub fn caps(self, caps: bool) -> Cylinder {
+        Cylinder { caps, ..self }
+    }
+
+    /// Return a modified version of the cylinder with or without rim
+    /// circles.
+    pub fn rims(self, rims: bool) -> Cylinder {
+        Cylinder { rims, ..self }
+    }
+
+    /// Return a modified version of the cylinder with rings stacked
+    /// `spacing` apart along its axis, starting from the bottom rim.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `spacing` is not positive and finite.
+    pub fn ring_spacing(self, spacing: Option<f64>) -> Cylinder {
+        if let Some(spacing) = spacing {
+            assert_spacing(spacing, "ring spacing");
+        }
+        Cylinder {
+            ring_spacing: spacing,
+            ..self
+        }
+    }
+
+    /// Return a modified version of the cylinder with generator lines
+    /// `angle` radians apart around its axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `angle` is not positive and finite.
+    pub fn generator_angle(self, angle: Option<f64>) -> Cylinder {
+        if let Some(angle) = angle {
+            assert_spacing(angle, "generator angle");
+        }
+        Cylinder {
+            generator_angle: angle,
+            ..self
+        }
+    }
+
+    /// Return the world-space direction of the cylinder's axis.
+    fn axis(&self) -> Vector3<f64> {
+        self.transform * Vector3::y()
+    }
+
+    /// Return the time of impact of a ray against the cylinder, in
+    /// the local space of the cylinder, shrunk by `OCCLUSION_SHRINK`.
+    fn local_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        // The caps move in by as much as the side, rather than by a
+        // fraction of the length, which would leave the ends of a long
+        // cylinder hiding nothing.
+        let inset = self.radius.min(self.half_height) * (1.0 - OCCLUSION_SHRINK);
+        let radius = self.radius - inset;
+        let half_height = self.half_height - inset;
+        let (o, d) = (ray.origin, ray.dir);
+        let mut hits = vec![];
+
+        // side of the cylinder
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - radius * radius;
+        let disc = b * b - 4.0 * a * c;
+        if a > 0.0 && disc >= 0.0 {
+            let sq = disc.sqrt();
+            for t in &[(-b - sq) / (2.0 * a), (-b + sq) / (2.0 * a)] {
+                if (o.y + t * d.y).abs() <= half_height {
+                    hits.push(*t);
+                }
+            }
+        }
+
+        // caps at either end
+        if self.caps && d.y != 0.0 {
+            for y in &[-half_height, half_height] {
+                let t = (y - o.y) / d.y;
+                let (x, z) = (o.x + t * d.x, o.z + t * d.z);
+                if x * x + z * z <= radius * radius {
+                    hits.push(t);
+                }
+            }
+        }
+        nearest_hit(hits, max_toi)
+    }
+}
+
+impl Shape for Cylinder {
+    fn name(&self) -> String {
+        "Cylinder".to_string()
+    }
+
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.local_intersect(&ray.inverse_transform_by(&self.transform), max_toi)
+    }
+
+    /// Uncapped cylinders still contain the points between their
+    /// ends.
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        let p = self.transform.inverse_transform_point(point);
+        p.y.abs() <= self.half_height + eps && p.x.hypot(p.z) <= self.radius + eps
+    }
+
+    fn paths(&self, _camera: &Camera) -> Paths {
+        let h = self.half_height;
+        let mut paths = vec![];
+        if self.rims {
+            paths.push(ring_path(&self.transform, -h, self.radius));
+            paths.push(ring_path(&self.transform, h, self.radius));
+        }
+        if let Some(spacing) = self.ring_spacing {
+            let mut y = -h + spacing;
+            while y < h - spacing * 1e-3 {
+                paths.push(ring_path(&self.transform, y, self.radius));
+                y += spacing;
+            }
+        }
+        if let Some(angle) = self.generator_angle {
+            for theta in even_angles(angle) {
+                let (s, c) = theta.sin_cos();
+                let (x, z) = (self.radius * s, self.radius * c);
+                paths.push(vec![
+                    self.transform * Point3::new(x, -h, z),
+                    self.transform * Point3::new(x, h, z),
+                ]);
+            }
+        }
+        paths
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        let axis = self.axis();
+        let center: Point3<f64> = self.transform.translation.vector.into();
+        let bottom = disc_aabb(&(center - axis * self.half_height), &axis, self.radius);
+        let top = disc_aabb(&(center + axis * self.half_height), &axis, self.radius);
+        bottom.merged(&top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn tilted_cylinder_intersection() {
+        let p0 = Point3::new(0.0, 0.0, 0.0);
+        let p1 = Point3::new(4.0, 0.0, 0.0);
+        let cylinder = Cylinder::from_endpoints(&p0, &p1, 1.0);
+
+        // through the side
+        let ray = Ray::new(Point3::new(2.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_relative_eq!(cylinder.intersect(&ray, 10.0).unwrap(), 4.01);
+
+        // through a cap
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(cylinder.intersect(&ray, 10.0).unwrap(), 5.01);
+        let open = cylinder.clone().caps(false);
+        assert!(open.intersect(&ray, 10.0).is_none());
+
+        // past the end
+        let ray = Ray::new(Point3::new(4.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.intersect(&ray, 10.0).is_none());
+
+        let bb = cylinder.bounding_box();
+        assert!((bb.mins - Point3::new(0.0, -1.0, -1.0)).norm() < 1e-9);
+        assert!((bb.maxs - Point3::new(4.0, 1.0, 1.0)).norm() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "ring spacing must be positive")]
+    fn zero_ring_spacing() {
+        Cylinder::new(&Point3::origin(), 1.0, 1.0).ring_spacing(Some(0.0));
+    }
+
+    #[test]
+    fn long_cylinder_occludes_to_its_ends() {
+        let cylinder = Cylinder::new(&Point3::origin(), 1.0, 50.0);
+
+        // through the side, and the cap, near the end
+        let ray = Ray::new(Point3::new(5.0, 49.7, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert_relative_eq!(
+            cylinder.intersect(&ray, 10.0).unwrap(),
+            4.01,
+            epsilon = 1e-9
+        );
+        let ray = Ray::new(Point3::new(0.0, 55.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_relative_eq!(
+            cylinder.intersect(&ray, 10.0).unwrap(),
+            5.01,
+            epsilon = 1e-9
+        );
+    }
+}
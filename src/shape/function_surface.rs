@@ -0,0 +1,416 @@
+//! Height-field surfaces defined by a function `y = f(x, z)`.
+use crate::common::*;
+use crate::shape::{assert_spacing, Camera, Path, Paths, Shape, OCCLUSION_SHRINK};
+use crate::util::ray_aabb_interval;
+use std::sync::Arc;
+
+/// Direction of the strokes drawn across a `FunctionSurface`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceStrokes {
+    /// Strokes running along both the x- and z-axes.
+    Grid,
+
+    /// Strokes running along the x-axis, at regular intervals in z.
+    AlongX,
+
+    /// Strokes running along the z-axis, at regular intervals in x.
+    AlongZ,
+}
+
+/// Surface of the height field `y = f(x, z)` over a rectangular
+/// domain.
+///
+/// For occlusion, the surface is treated as the top of a solid that
+/// extends down to the bottom of its bounding box, in the spirit of
+/// ln's `Function` shape. Rays are intersected by marching across the
+/// domain and refining any crossing by bisection.
+#[derive(Clone)]
+pub struct FunctionSurface {
+    func: Arc<dyn Fn(f64, f64) -> f64 + Send + Sync>,
+
+    /// bounds of the domain, as (x, z) pairs
+    min: Point2<f64>,
+    max: Point2<f64>,
+
+    /// bounds on the value of the function over the domain
+    y_min: f64,
+    y_max: f64,
+
+    strokes: SurfaceStrokes,
+
+    /// distance between adjacent strokes
+    spacing: f64,
+
+    /// distance between samples along strokes, and while ray
+    /// marching
+    step: f64,
+
+    /// distance the occluding surface is lowered below the function
+    offset: f64,
+}
+
+impl FunctionSurface {
+    /// Create a surface for `func` over the domain spanning `min` to
+    /// `max`, given as (x, z) pairs.
+    ///
+    /// The range of the function is estimated by sampling it across
+    /// the domain; use `y_bounds` if the estimate is too tight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the domain is empty.
+    pub fn new<F>(func: F, min: Point2<f64>, max: Point2<f64>) -> FunctionSurface
+    where
+        F: Fn(f64, f64) -> f64 + Send + Sync + 'static,
+    {
+        assert!(
+            max.x > min.x && max.y > min.y,
+            "domain of a FunctionSurface must not be empty"
+        );
+        const N_SAMPLES: usize = 64;
+        let size = max - min;
+        let mut y_min = f64::INFINITY;
+        let mut y_max = f64::NEG_INFINITY;
+        for i in 0..=N_SAMPLES {
+            for j in 0..=N_SAMPLES {
+                let x = min.x + size.x * i as f64 / N_SAMPLES as f64;
+                let z = min.y + size.y * j as f64 / N_SAMPLES as f64;
+                let y = func(x, z);
+                y_min = y_min.min(y);
+                y_max = y_max.max(y);
+            }
+        }
+        // pad the estimate, since peaks may fall between samples
+        let pad = (y_max - y_min) * 0.05 + 1e-6;
+
+        FunctionSurface {
+            func: Arc::new(func),
+            min,
+            max,
+            y_min: y_min - pad,
+            y_max: y_max + pad,
+            strokes: SurfaceStrokes::Grid,
+            spacing: size.x.min(size.y) / 20.0,
+            step: 0.0,
+            offset: 0.0,
+        }
+        .step(size.x.min(size.y) / 200.0)
+    }
+
+    /// Return a modified version of the surface with explicit bounds
+    /// on the value of the function.
+    ///
+    /// The bounding box reaches below `y_min` by however far the
+    /// occluding surface is lowered.
+    pub fn y_bounds(self, y_min: f64, y_max: f64) -> FunctionSurface {
+        FunctionSurface {
+            y_min,
+            y_max,
+            ..self
+        }
+    }
+
+    /// Return a modified version of the surface with strokes in the
+    /// given direction(s).
+    pub fn strokes(self, strokes: SurfaceStrokes) -> FunctionSurface {
+        FunctionSurface { strokes, ..self }
+    }
+
+    /// Return a modified version of the surface with strokes
+    /// `spacing` apart.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `spacing` is not positive and finite.
+    pub fn spacing(self, spacing: f64) -> FunctionSurface {
+        assert_spacing(spacing, "stroke spacing");
+        FunctionSurface { spacing, ..self }
+    }
+
+    /// Return a modified version of the surface, sampled every `step`
+    /// along each stroke and while intersecting rays.
+    ///
+    /// Features of the function smaller than `step` may be missed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is not positive and finite.
+    pub fn step(self, step: f64) -> FunctionSurface {
+        assert_spacing(step, "step");
+        let surface = FunctionSurface { step, ..self };
+        FunctionSurface {
+            offset: surface.occlusion_offset(),
+            ..surface
+        }
+    }
+
+    /// Return the height of the surface at (x, z).
+    pub fn height(&self, x: f64, z: f64) -> f64 {
+        (self.func)(x, z)
+    }
+
+    /// Return the signed height of a point above the surface used for
+    /// occlusion.
+    ///
+    /// The surface is lowered by `offset`, so that it doesn't occlude
+    /// its own strokes.
+    fn height_above(&self, p: &Point3<f64>) -> f64 {
+        p.y - (self.height(p.x, p.z) - self.offset)
+    }
+
+    /// Return how far to lower the occluding surface.
+    ///
+    /// Strokes are straight between samples, so where the surface is
+    /// convex they dip below it by up to the sagitta of a chord `step`
+    /// long. That is estimated by sampling across the domain, and
+    /// padded since the sharpest curvature may fall between samples,
+    /// with a margin of `OCCLUSION_SHRINK` on top.
+    fn occlusion_offset(&self) -> f64 {
+        const N_SAMPLES: usize = 64;
+        let size = self.max - self.min;
+        let h = self.step * 0.5;
+        let mut sagitta: f64 = 0.0;
+        for i in 0..=N_SAMPLES {
+            for j in 0..=N_SAMPLES {
+                let x = self.min.x + size.x * i as f64 / N_SAMPLES as f64;
+                let z = self.min.y + size.y * j as f64 / N_SAMPLES as f64;
+                let y = self.height(x, z);
+                let along_x = y - 0.5 * (self.height(x - h, z) + self.height(x + h, z));
+                let along_z = y - 0.5 * (self.height(x, z - h) + self.height(x, z + h));
+                sagitta = sagitta.max(along_x).max(along_z);
+            }
+        }
+        sagitta * 1.5 + self.step * (1.0 - OCCLUSION_SHRINK)
+    }
+
+    /// Return a stroke across the surface from (x0, z0) to (x1, z1).
+    fn stroke(&self, x0: f64, z0: f64, x1: f64, z1: f64) -> Path {
+        let length = ((x1 - x0).powi(2) + (z1 - z0).powi(2)).sqrt();
+        let n = ((length / self.step).ceil() as usize).max(1);
+        (0..=n)
+            .map(|i| {
+                let s = i as f64 / n as f64;
+                let (x, z) = (x0 + (x1 - x0) * s, z0 + (z1 - z0) * s);
+                Point3::new(x, self.height(x, z), z)
+            })
+            .collect()
+    }
+
+    /// Return evenly spaced values from `min` to `max` inclusive, no
+    /// more than `spacing` apart.
+    fn stroke_offsets(&self, min: f64, max: f64) -> Vec<f64> {
+        let n = (((max - min) / self.spacing).round() as usize).max(1);
+        (0..=n)
+            .map(|i| min + (max - min) * i as f64 / n as f64)
+            .collect()
+    }
+
+    /// Return the time of impact of the ray with the solid below the
+    /// surface or, unless `solid`, with the surface on the way out of
+    /// a ray starting inside it.
+    fn toi_with_ray(&self, ray: &Ray<f64>, max_toi: f64, solid: bool) -> Option<f64> {
+        let (t_enter, t_exit) = ray_aabb_interval(&self.bounding_box(), ray)?;
+        let t0 = t_enter.max(0.0);
+        let t1 = t_exit.min(max_toi);
+        if t0 > t1 {
+            return None;
+        }
+
+        // Entering the solid through the sides or bottom of its box.
+        let starts_inside = self.height_above(&ray.point_at(t0)) <= 0.0;
+        if starts_inside && (solid || t_enter >= 0.0) {
+            return Some(t0);
+        }
+
+        // March in steps of roughly `step` across the domain, looking
+        // for the surface on the way in or out of the solid.
+        let speed = ray.dir.x.hypot(ray.dir.z);
+        let n = if speed > 0.0 {
+            (((t1 - t0) * speed / self.step).ceil() as usize).max(1)
+        } else {
+            1
+        };
+        let mut prev_t = t0;
+        for i in 1..=n {
+            let t = t0 + (t1 - t0) * i as f64 / n as f64;
+            let inside = self.height_above(&ray.point_at(t)) <= 0.0;
+            if inside != starts_inside {
+                // refine the crossing between the last two samples
+                let (mut lo, mut hi) = (prev_t, t);
+                for _ in 0..50 {
+                    let mid = 0.5 * (lo + hi);
+                    if (self.height_above(&ray.point_at(mid)) <= 0.0) == starts_inside {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                return Some(hi);
+            }
+            prev_t = t;
+        }
+
+        // Leaving the solid through the sides or bottom of its box.
+        if starts_inside && t_exit <= max_toi {
+            Some(t_exit)
+        } else {
+            None
+        }
+    }
+}
+
+impl Shape for FunctionSurface {
+    fn name(&self) -> String {
+        "FunctionSurface".to_string()
+    }
+
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.toi_with_ray(ray, max_toi, true)
+    }
+
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.toi_with_ray(ray, max_toi, false)
+    }
+
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        let bb = self.bounding_box();
+        (0..3).all(|i| point[i] >= bb.mins[i] - eps && point[i] <= bb.maxs[i] + eps)
+            && point.y
+                <= self.height(
+                    point.x.clamp(self.min.x, self.max.x),
+                    point.z.clamp(self.min.y, self.max.y),
+                ) + eps
+    }
+
+    fn paths(&self, _camera: &Camera) -> Paths {
+        let mut paths = vec![];
+        if self.strokes != SurfaceStrokes::AlongZ {
+            for z in self.stroke_offsets(self.min.y, self.max.y) {
+                paths.push(self.stroke(self.min.x, z, self.max.x, z));
+            }
+        }
+        if self.strokes != SurfaceStrokes::AlongX {
+            for x in self.stroke_offsets(self.min.x, self.max.x) {
+                paths.push(self.stroke(x, self.min.y, x, self.max.y));
+            }
+        }
+        paths
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        AABB::new(
+            Point3::new(self.min.x, self.y_min - self.offset, self.min.y),
+            Point3::new(self.max.x, self.y_max, self.max.y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::SceneBuilder;
+    use crate::shape::Sphere;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn ray_marched_intersection() {
+        let surface = FunctionSurface::new(
+            |x, z| (x * x + z * z) * 0.25,
+            Point2::new(-2.0, -2.0),
+            Point2::new(2.0, 2.0),
+        );
+
+        // straight down onto the bowl
+        let ray = Ray::new(Point3::new(1.0, 5.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+        let toi = surface.intersect(&ray, 10.0).unwrap();
+        assert_relative_eq!(toi, 4.5, epsilon = 1e-3);
+
+        // across the inside of the bowl
+        let ray = Ray::new(Point3::new(0.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let toi = surface.intersect(&ray, 10.0).unwrap();
+        assert_relative_eq!(toi, 2.0f64.sqrt(), epsilon = 1e-3);
+
+        // through the side of the solid, below the rim
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 1.9), Vector3::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(surface.intersect(&ray, 10.0).unwrap(), 3.0);
+
+        // above everything
+        let ray = Ray::new(Point3::new(-5.0, 3.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(surface.intersect(&ray, 10.0).is_none());
+
+        assert_eq!(surface.paths(&Camera::new()).len(), 42);
+    }
+
+    #[test]
+    fn rays_starting_below_the_surface() {
+        let surface = FunctionSurface::new(
+            |x, z| (x * x + z * z) * 0.25,
+            Point2::new(-2.0, -2.0),
+            Point2::new(2.0, 2.0),
+        );
+
+        // the solid hits at once, its surface where the ray leaves
+        let ray = Ray::new(Point3::new(1.0, 0.1, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(surface.intersect(&ray, 10.0), Some(0.0));
+        let toi = surface.surface_intersect(&ray, 10.0).unwrap();
+        assert_relative_eq!(toi, 1.0 - 0.4f64.sqrt(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn convex_strokes_stay_whole() {
+        let dome = FunctionSurface::new(
+            |x, z| 2.0 - 0.5 * (x * x + z * z),
+            Point2::new(-2.0, -2.0),
+            Point2::new(2.0, 2.0),
+        )
+        .spacing(1.0)
+        .step(0.2);
+        let scene = SceneBuilder::new().add(dome).build();
+        let camera = Camera::new()
+            .look_at(
+                &Point3::new(4.0, 5.0, 6.0),
+                &Point3::new(0.0, 0.0, 0.0),
+                &Vector3::new(0.0, 1.0, 0.0),
+            )
+            .perspective(std::f64::consts::FRAC_PI_3, 1.0, 1.0, 30.0);
+
+        // Every stroke on the dome is in view, and none are broken up
+        // by the surface beneath them.
+        assert_eq!(scene.render(&camera).len(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "stroke spacing must be positive")]
+    fn zero_spacing() {
+        FunctionSurface::new(|_, _| 0.0, Point2::new(0.0, 0.0), Point2::new(1.0, 1.0)).spacing(0.0);
+    }
+
+    #[test]
+    fn flat_surface_occludes() {
+        let ground =
+            FunctionSurface::new(|_, _| 0.0, Point2::new(-2.0, -2.0), Point2::new(2.0, 2.0));
+        let ray = Ray::new(Point3::new(0.1, 5.0, 0.2), Vector3::new(0.0, -1.0, 0.0));
+        assert!(ground.intersect(&ray, 10.0).is_some());
+
+        // A sphere under the ground is hidden from above.
+        let scene = SceneBuilder::new()
+            .add(ground)
+            .add(Sphere::new(
+                &Point3::new(0.0, -1.0, 0.0),
+                0.5,
+                Some(0.5),
+                None,
+            ))
+            .build();
+        let camera = Camera::new()
+            .look_at(
+                &Point3::new(0.5, 5.0, 1.0),
+                &Point3::new(0.0, 0.0, 0.0),
+                &Vector3::new(0.0, 1.0, 0.0),
+            )
+            .perspective(std::f64::consts::FRAC_PI_3, 1.0, 1.0, 30.0);
+        let paths = scene.render(&camera);
+        assert!(!paths.is_empty());
+        assert!(paths.iter().all(|p| p.shape.index == 0));
+    }
+}
@@ -0,0 +1,355 @@
+//! Heightmaps loaded from grayscale images, for terrain surfaces.
+use crate::common::*;
+use crate::error::LoadError;
+use crate::shape::FunctionSurface;
+use std::io::Read;
+use std::sync::Arc;
+
+/// Grid of heights, normalized to [0, 1], typically loaded from a
+/// grayscale image.
+///
+/// Convert it into a shape with `to_surface`.
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    width: usize,
+    height: usize,
+
+    /// row-major heights, with rows running along the z-axis
+    values: Vec<f64>,
+}
+
+impl Heightmap {
+    /// Create a heightmap from row-major `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of values doesn't match the dimensions,
+    /// or either dimension is less than 2.
+    pub fn new(width: usize, height: usize, values: Vec<f64>) -> Heightmap {
+        assert!(width >= 2 && height >= 2, "heightmap must be at least 2x2");
+        assert_eq!(values.len(), width * height);
+        Heightmap {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Load a heightmap from a PGM file, or a PNG file when the `png`
+    /// feature is enabled, based on the file extension.
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Heightmap, LoadError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        match extension.as_deref() {
+            Some("pgm") => Heightmap::parse_pgm(file),
+            #[cfg(feature = "png")]
+            Some("png") => Heightmap::parse_png(file),
+            _ => Err(LoadError::Unsupported(format!(
+                "no heightmap loader for {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Parse a heightmap from binary (P5) or ASCII (P2) PGM data.
+    pub fn parse_pgm<R: Read>(mut reader: R) -> Result<Heightmap, LoadError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        let mut header = PgmHeader {
+            data: &data,
+            pos: 0,
+            line: 1,
+        };
+        let magic = header.token()?;
+        if magic != "P2" && magic != "P5" {
+            return Err(LoadError::Unsupported(format!("PGM variant {}", magic)));
+        }
+        let width = header.number()?;
+        let height = header.number()?;
+        let max_value = header.number()?;
+        if width < 2 || height < 2 || max_value == 0 || max_value > 65535 {
+            return Err(LoadError::parse(header.line, "invalid PGM dimensions"));
+        }
+
+        let n = width
+            .checked_mul(height)
+            .ok_or_else(|| LoadError::parse(header.line, "PGM dimensions too large"))?;
+        let raw: Vec<usize> = if magic == "P2" {
+            (0..n).map(|_| header.number()).collect::<Result<_, _>>()?
+        } else {
+            // a single whitespace character separates the header from
+            // the pixel data
+            let start = header.pos + 1;
+            let bytes_per_value = if max_value < 256 { 1 } else { 2 };
+            let expected = n
+                .checked_mul(bytes_per_value)
+                .and_then(|size| size.checked_add(start))
+                .ok_or_else(|| LoadError::parse(header.line, "PGM dimensions too large"))?;
+            if data.len() < expected {
+                return Err(LoadError::Truncated {
+                    expected,
+                    found: data.len(),
+                });
+            }
+            data[start..expected]
+                .chunks(bytes_per_value)
+                .map(|c| c.iter().fold(0, |v, b| (v << 8) | *b as usize))
+                .collect()
+        };
+
+        let values = raw
+            .into_iter()
+            .map(|v| v.min(max_value) as f64 / max_value as f64)
+            .collect();
+        Ok(Heightmap::new(width, height, values))
+    }
+
+    /// Parse a heightmap from PNG data.
+    ///
+    /// Color images are converted to grayscale by averaging their
+    /// channels; alpha is ignored.
+    #[cfg(feature = "png")]
+    pub fn parse_png<R: Read>(reader: R) -> Result<Heightmap, LoadError> {
+        let image_error = |e: png::DecodingError| LoadError::Image(e.to_string());
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(image_error)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(image_error)?;
+
+        let (channels, max_value) = match info.bit_depth {
+            png::BitDepth::Sixteen => (info.line_size / info.width as usize / 2, 65535.0),
+            _ => (info.line_size / info.width as usize, 255.0),
+        };
+        let color_channels = match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => 1,
+            _ => 3,
+        };
+        let bytes_per_sample = if max_value > 255.0 { 2 } else { 1 };
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        if width < 2 || height < 2 {
+            return Err(LoadError::Image(
+                "heightmap must be at least 2x2".to_string(),
+            ));
+        }
+        let mut values = Vec::with_capacity(width * height);
+        for row in buf.chunks(info.line_size).take(height) {
+            for pixel in row.chunks(channels * bytes_per_sample).take(width) {
+                let sum: f64 = pixel
+                    .chunks(bytes_per_sample)
+                    .take(color_channels)
+                    .map(|s| s.iter().fold(0, |v, b| (v << 8) | *b as usize) as f64)
+                    .sum();
+                values.push(sum / color_channels as f64 / max_value);
+            }
+        }
+        Ok(Heightmap::new(width, height, values))
+    }
+
+    /// Return the dimensions of the heightmap, in samples.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Return the bilinearly interpolated height at (u, v), each in
+    /// [0, 1] across the width and height of the map.
+    pub fn sample(&self, u: f64, v: f64) -> f64 {
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f64;
+        let y = v.clamp(0.0, 1.0) * (self.height - 1) as f64;
+        let (x0, y0) = (
+            (x.floor() as usize).min(self.width - 2),
+            (y.floor() as usize).min(self.height - 2),
+        );
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+        let at = |i: usize, j: usize| self.values[j * self.width + i];
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+        let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Convert the heightmap into a surface.
+    ///
+    /// The map covers `scale.x` ✕ `scale.z` in the xz-plane, with
+    /// normalized heights multiplied by `scale.y`. The whole surface
+    /// is then moved by `offset`, so its corner lies at `offset`.
+    ///
+    /// Negative scales mirror the map, so that the first sample lies at
+    /// the far side of the surface.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` isn't finite, or if `scale.x` or `scale.z` is
+    /// zero.
+    pub fn to_surface(&self, scale: &Vector3<f64>, offset: &Vector3<f64>) -> FunctionSurface {
+        assert!(
+            scale.iter().all(|s| s.is_finite()) && scale.x != 0.0 && scale.z != 0.0,
+            "heightmap must cover a finite, non-empty area"
+        );
+        let (lo, hi) = self
+            .values
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(*v), hi.max(*v))
+            });
+        let (y0, y1) = (offset.y + scale.y * lo, offset.y + scale.y * hi);
+
+        // sample finely enough to resolve every cell of the map
+        let cell =
+            (scale.x.abs() / (self.width - 1) as f64).min(scale.z.abs() / (self.height - 1) as f64);
+
+        let map = Arc::new(self.clone());
+        let (scale, offset) = (*scale, *offset);
+        let (corner, far_corner) = (
+            Point2::new(offset.x, offset.z),
+            Point2::new(offset.x + scale.x, offset.z + scale.z),
+        );
+        FunctionSurface::new(
+            move |x, z| {
+                let (u, v) = ((x - offset.x) / scale.x, (z - offset.z) / scale.z);
+                offset.y + scale.y * map.sample(u, v)
+            },
+            corner.inf(&far_corner),
+            corner.sup(&far_corner),
+        )
+        // The bounding box of the surface reaches below these bounds
+        // by its own occlusion offset.
+        .y_bounds(y0.min(y1), y0.max(y1))
+        .step(cell * 0.5)
+    }
+}
+
+/// Reader for the whitespace-separated tokens in the header of a PGM
+/// file.
+struct PgmHeader<'a> {
+    data: &'a [u8],
+    pos: usize,
+
+    /// one-based line number at `pos`, for errors
+    line: usize,
+}
+
+impl<'a> PgmHeader<'a> {
+    /// Return the next token, skipping whitespace and comments (which
+    /// run from '#' to the end of the line).
+    fn token(&mut self) -> Result<String, LoadError> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|c| *c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => {
+                    if *c == b'\n' {
+                        self.line += 1;
+                    }
+                    self.pos += 1;
+                }
+                Some(_) => break,
+                None => return Err(LoadError::parse(self.line, "unexpected end of PGM data")),
+            }
+        }
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|c| !c.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        Ok(String::from_utf8_lossy(&self.data[start..self.pos]).into_owned())
+    }
+
+    /// Return the next token as a number.
+    fn number(&mut self) -> Result<usize, LoadError> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| LoadError::parse(self.line, format!("invalid PGM value '{}'", token)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Shape;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn pgm_heightmap() {
+        let ascii = "P2\n# a ramp\n3 2\n4\n0 2 4\n0 2 4\n";
+        let map = Heightmap::parse_pgm(ascii.as_bytes()).unwrap();
+        assert_eq!(map.dimensions(), (3, 2));
+        assert_relative_eq!(map.sample(0.25, 0.5), 0.25);
+
+        let mut binary = b"P5 3 2 4\n".to_vec();
+        binary.extend_from_slice(&[0, 2, 4, 0, 2, 4]);
+        let binary_map = Heightmap::parse_pgm(&binary[..]).unwrap();
+        assert_eq!(binary_map.values, map.values);
+
+        let surface = map.to_surface(&Vector3::new(10.0, 2.0, 5.0), &Vector3::new(-5.0, 1.0, 0.0));
+        assert_relative_eq!(surface.height(0.0, 2.0), 2.0);
+        let bb = surface.bounding_box();
+        assert!(bb.mins.x == -5.0 && bb.maxs.x == 5.0 && bb.maxs.z == 5.0);
+        assert!(bb.mins.y < 1.0 && bb.maxs.y >= 3.0);
+
+        assert!(Heightmap::parse_pgm(&binary[..binary.len() - 1]).is_err());
+
+        // Mirrored along x, the ramp falls instead of rising.
+        let surface = map.to_surface(&Vector3::new(-10.0, 2.0, 5.0), &Vector3::new(5.0, 1.0, 0.0));
+        assert_relative_eq!(surface.height(-2.5, 2.0), 2.5);
+        let bb = surface.bounding_box();
+        assert!(bb.mins.x == -5.0 && bb.maxs.x == 5.0 && bb.maxs.z == 5.0);
+    }
+
+    #[test]
+    fn pgm_errors() {
+        let line = |data: &[u8]| match Heightmap::parse_pgm(data) {
+            Err(LoadError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        assert_eq!(line(b"P2\n# comment\n3 x\n4\n"), 3);
+        assert_eq!(line(b"P2\n3 2\n4\n0 2 4\n0 2"), 5);
+        assert_eq!(line(b"P5\n1 2\n255\n"), 3);
+
+        // Dimensions whose product overflows are rejected, rather
+        // than wrapping around.
+        let huge = format!("P5\n{} {}\n65535\n", usize::MAX / 2, 3);
+        assert_eq!(line(huge.as_bytes()), 3);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_heightmap() {
+        // A 3x2 RGB ramp, brightening across each row.
+        let mut data = vec![];
+        let mut encoder = png::Encoder::new(&mut data, 3, 2);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        let row = [0, 0, 0, 51, 102, 153, 255, 255, 255];
+        writer.write_image_data(&[row, row].concat()).unwrap();
+        writer.finish().unwrap();
+
+        let map = Heightmap::parse_png(&data[..]).unwrap();
+        assert_eq!(map.dimensions(), (3, 2));
+        assert_relative_eq!(map.sample(0.0, 0.0), 0.0);
+        assert_relative_eq!(map.sample(0.5, 1.0), 0.4);
+        assert_relative_eq!(map.sample(1.0, 0.5), 1.0);
+
+        assert!(Heightmap::parse_png(&data[..data.len() / 2]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "heightmap must cover a finite, non-empty area")]
+    fn infinite_scale() {
+        let map = Heightmap::parse_pgm(&b"P2 2 2 1\n0 1\n1 0\n"[..]).unwrap();
+        map.to_surface(&Vector3::new(f64::INFINITY, 1.0, 1.0), &Vector3::zeros());
+    }
+}
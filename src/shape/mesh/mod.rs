@@ -0,0 +1,707 @@
+//! Triangle meshes, drawn as their edges or silhouettes.
+mod obj;
+mod stl;
+
+use crate::common::*;
+use crate::shape::{assert_spacing, Camera, Paths, Shape};
+use na::Isometry;
+use ncollide3d::{
+    bounding_volume::{self, BoundingVolume},
+    partitioning::{VisitStatus, Visitor, BVH, BVT},
+    query::PointQuery,
+    shape::Triangle,
+};
+use std::collections::HashMap;
+
+/// Selects which edges of a `Mesh` are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeMode {
+    /// Draw every edge of every triangle.
+    Wireframe,
+
+    /// Draw only the edges between a triangle facing the camera and
+    /// one facing away from it, along with boundary edges.
+    ///
+    /// Relies on the triangles of the mesh being wound consistently.
+    Silhouette,
+
+    /// Draw only the edges where the angle between the normals of
+    /// adjacent triangles exceeds the given threshold (in radians),
+    /// along with boundary edges.
+    ///
+    /// Smoothly tessellated surfaces then contribute only their
+    /// sharp features.
+    Crease(f64),
+
+    /// Draw both silhouette and crease edges, as well as boundary
+    /// edges.
+    Outline(f64),
+}
+
+/// Edge of a mesh, along with the triangles sharing it.
+struct MeshEdge {
+    /// vertex indices, in ascending order
+    vertices: [usize; 2],
+
+    /// indices of the triangles containing this edge
+    faces: Vec<usize>,
+}
+
+/// Indexed triangle mesh with paths along its edges.
+///
+/// Ray-intersection is accelerated with a bounding-volume tree over
+/// the triangles of the mesh.
+pub struct Mesh {
+    vertices: Vec<Point3<f64>>,
+    triangles: Vec<[usize; 3]>,
+
+    /// unnormalized normals of each triangle, following the
+    /// counter-clockwise winding of its vertices
+    normals: Vec<Vector3<f64>>,
+
+    /// unique edges of the mesh
+    edges: Vec<MeshEdge>,
+
+    /// vertices moved slightly into the mesh, forming the surface
+    /// used for occlusion
+    occluder: Vec<Point3<f64>>,
+
+    edge_mode: EdgeMode,
+
+    /// bounded-volume tree over triangle indices, or `None` for an
+    /// empty mesh
+    bvt: Option<BVT<usize, AABB<f64>>>,
+
+    aabb: AABB<f64>,
+}
+
+impl Mesh {
+    /// Create a mesh from a list of vertices and triangles indexing
+    /// into them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any triangle refers to a vertex out of range.
+    pub fn new(vertices: Vec<Point3<f64>>, triangles: Vec<[usize; 3]>) -> Mesh {
+        for t in &triangles {
+            assert!(
+                t.iter().all(|i| *i < vertices.len()),
+                "triangle {:?} refers to a missing vertex",
+                t
+            );
+        }
+
+        let mut edge_indices = HashMap::new();
+        let mut edges: Vec<MeshEdge> = vec![];
+        for (i, t) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                if a == b {
+                    continue;
+                }
+                let vertices = [a.min(b), a.max(b)];
+                let index = *edge_indices.entry(vertices).or_insert_with(|| {
+                    edges.push(MeshEdge {
+                        vertices,
+                        faces: vec![],
+                    });
+                    edges.len() - 1
+                });
+                edges[index].faces.push(i);
+            }
+        }
+        let normals: Vec<_> = triangles
+            .iter()
+            .map(|t| {
+                let (a, b, c) = (vertices[t[0]], vertices[t[1]], vertices[t[2]]);
+                (b - a).cross(&(c - a))
+            })
+            .collect();
+        let occluder = occluder_vertices(&vertices, &triangles, &normals, &edges);
+
+        let leaves: Vec<_> = triangles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let bb = bounding_volume::local_point_cloud_aabb(&[
+                    vertices[t[0]],
+                    vertices[t[1]],
+                    vertices[t[2]],
+                    occluder[t[0]],
+                    occluder[t[1]],
+                    occluder[t[2]],
+                ]);
+                (i, bb)
+            })
+            .collect();
+        let aabb = leaves
+            .iter()
+            .map(|(_, bb)| *bb)
+            .reduce(|a, b| a.merged(&b))
+            .unwrap_or_else(|| AABB::new(Point3::origin(), Point3::origin()));
+        let bvt = if leaves.is_empty() {
+            None
+        } else {
+            Some(BVT::new_balanced(leaves))
+        };
+
+        Mesh {
+            vertices,
+            triangles,
+            normals,
+            edges,
+            occluder,
+            edge_mode: EdgeMode::Wireframe,
+            bvt,
+            aabb,
+        }
+    }
+
+    /// Return a version of the mesh with vertices that lie within
+    /// `tolerance` of each other merged into one, so that shared
+    /// edges are only drawn once.
+    ///
+    /// Triangles that collapse as a result are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` isn't positive and finite.
+    pub fn welded(self, tolerance: f64) -> Mesh {
+        assert_spacing(tolerance, "weld tolerance");
+
+        // Vertices are bucketed into cells as wide as the tolerance, so
+        // any match lies in the same or a neighboring cell.
+        let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut vertices: Vec<Point3<f64>> = vec![];
+        let remap: Vec<usize> = self
+            .vertices
+            .iter()
+            .map(|v| {
+                let cell = [
+                    (v.x / tolerance).floor() as i64,
+                    (v.y / tolerance).floor() as i64,
+                    (v.z / tolerance).floor() as i64,
+                ];
+                let mut neighbors = (-1..=1)
+                    .flat_map(|i| (-1..=1).flat_map(move |j| (-1..=1).map(move |k| [i, j, k])));
+                let existing = neighbors.find_map(|[i, j, k]| {
+                    cells
+                        .get(&[cell[0] + i, cell[1] + j, cell[2] + k])?
+                        .iter()
+                        .copied()
+                        .find(|w| (vertices[*w] - v).norm() <= tolerance)
+                });
+                existing.unwrap_or_else(|| {
+                    vertices.push(*v);
+                    cells.entry(cell).or_default().push(vertices.len() - 1);
+                    vertices.len() - 1
+                })
+            })
+            .collect();
+        let triangles = self
+            .triangles
+            .iter()
+            .map(|t| [remap[t[0]], remap[t[1]], remap[t[2]]])
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .collect();
+        Mesh::new(vertices, triangles).edge_mode(self.edge_mode)
+    }
+
+    /// Return a modified version of the mesh drawing the edges
+    /// selected by `mode`.
+    pub fn edge_mode(self, mode: EdgeMode) -> Mesh {
+        Mesh {
+            edge_mode: mode,
+            ..self
+        }
+    }
+
+    /// Return the vertices of the mesh.
+    pub fn vertices(&self) -> &[Point3<f64>] {
+        &self.vertices
+    }
+
+    /// Return the triangles of the mesh, as indices into `vertices`.
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    /// Return true if the edge separates triangles facing towards the
+    /// camera from those facing away, or bounds the mesh.
+    fn is_silhouette(&self, edge: &MeshEdge, camera: &Camera) -> bool {
+        if edge.faces.len() < 2 {
+            return true;
+        }
+        let midpoint = na::center(
+            &self.vertices[edge.vertices[0]],
+            &self.vertices[edge.vertices[1]],
+        );
+        let view = camera.view_direction(&midpoint);
+        let mut facing = edge.faces.iter().map(|f| self.normals[*f].dot(&view) > 0.0);
+        let first = facing.next().unwrap();
+        facing.any(|f| f != first)
+    }
+
+    /// Return true if the angle between the normals of the triangles
+    /// sharing the edge exceeds `threshold`, or the edge bounds the
+    /// mesh.
+    fn is_crease(&self, edge: &MeshEdge, threshold: f64) -> bool {
+        match edge.faces[..] {
+            [f0, f1] => {
+                let (n0, n1) = (self.normals[f0], self.normals[f1]);
+                n0.cross(&n1).norm().atan2(n0.dot(&n1)) > threshold
+            }
+            // Boundary and non-manifold edges are always features.
+            _ => true,
+        }
+    }
+
+    /// Return the triangle at index `i` as an ncollide shape.
+    fn triangle(&self, i: usize) -> Triangle<f64> {
+        let t = self.triangles[i];
+        Triangle::new(
+            self.vertices[t[0]],
+            self.vertices[t[1]],
+            self.vertices[t[2]],
+        )
+    }
+
+    /// Return the triangle at index `i` of the occluding surface.
+    fn occluding_triangle(&self, i: usize) -> Triangle<f64> {
+        let t = self.triangles[i];
+        Triangle::new(
+            self.occluder[t[0]],
+            self.occluder[t[1]],
+            self.occluder[t[2]],
+        )
+    }
+}
+
+/// Fraction of its shortest edge by which each vertex of the
+/// occluding surface moves into the mesh.
+///
+/// Unlike the sampled curves of other shapes, the edges of a mesh lie
+/// exactly on its surface, so the occluder only needs to clear
+/// rounding errors rather than shrink by `OCCLUSION_SHRINK`. Hidden
+/// edges meeting the silhouette show for about the offset over the
+/// sine of the grazing angle, so it is kept as small as that allows.
+const OCCLUDER_OFFSET: f64 = 1e-6;
+
+/// Return the vertices of a mesh moved inwards along their normals.
+///
+/// Each vertex moves by `OCCLUDER_OFFSET` times its shortest edge,
+/// which keeps the surface from folding over in thin or concave parts
+/// of the mesh.
+///
+/// The triangles of a closed mesh may be wound either way; the
+/// winding is taken from the sign of its volume.
+fn occluder_vertices(
+    vertices: &[Point3<f64>],
+    triangles: &[[usize; 3]],
+    normals: &[Vector3<f64>],
+    edges: &[MeshEdge],
+) -> Vec<Point3<f64>> {
+    let mut vertex_normals = vec![Vector3::zeros(); vertices.len()];
+    let mut volume = 0.0;
+    for (t, n) in triangles.iter().zip(normals) {
+        for i in t {
+            vertex_normals[*i] += n;
+        }
+        volume += vertices[t[0]].coords.dot(n);
+    }
+
+    let mut shortest = vec![f64::INFINITY; vertices.len()];
+    for e in edges {
+        let [a, b] = e.vertices;
+        let length = (vertices[a] - vertices[b]).norm();
+        shortest[a] = shortest[a].min(length);
+        shortest[b] = shortest[b].min(length);
+    }
+
+    let inward = if volume < 0.0 { 1.0 } else { -1.0 };
+    vertices
+        .iter()
+        .zip(vertex_normals)
+        .zip(shortest)
+        .map(|((v, n), length)| match n.try_normalize(0.0) {
+            Some(n) if length.is_finite() => v + n * (inward * OCCLUDER_OFFSET * length),
+            _ => *v,
+        })
+        .collect()
+}
+
+impl Shape for Mesh {
+    fn name(&self) -> String {
+        format!("Mesh ({} triangles)", self.triangles.len())
+    }
+
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        let bvt = self.bvt.as_ref()?;
+        let mut visitor = MeshRayVisitor {
+            mesh: self,
+            ray,
+            best_toi: max_toi,
+            hit: false,
+        };
+        bvt.visit(&mut visitor);
+        if visitor.hit {
+            Some(visitor.best_toi)
+        } else {
+            None
+        }
+    }
+
+    /// Points are inside if a ray from them crosses the surface an odd
+    /// number of times, so the mesh should be closed.
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        let bvt = match self.bvt.as_ref() {
+            Some(bvt) => bvt,
+            None => return false,
+        };
+        if eps != 0.0 {
+            let mut visitor = MeshPointVisitor {
+                mesh: self,
+                point,
+                radius: eps.abs(),
+                near: false,
+            };
+            bvt.visit(&mut visitor);
+            if visitor.near {
+                return eps > 0.0;
+            }
+        }
+
+        // An arbitrary direction, unlikely to graze any edges.
+        let ray = Ray::new(*point, Vector3::new(0.5773, 0.6213, 0.5299));
+        let mut visitor = MeshCrossingVisitor {
+            mesh: self,
+            ray: &ray,
+            crossings: 0,
+        };
+        bvt.visit(&mut visitor);
+        visitor.crossings % 2 == 1
+    }
+
+    fn paths(&self, camera: &Camera) -> Paths {
+        self.edges
+            .iter()
+            .filter(|e| match self.edge_mode {
+                EdgeMode::Wireframe => true,
+                EdgeMode::Silhouette => self.is_silhouette(e, camera),
+                EdgeMode::Crease(threshold) => self.is_crease(e, threshold),
+                EdgeMode::Outline(threshold) => {
+                    self.is_crease(e, threshold) || self.is_silhouette(e, camera)
+                }
+            })
+            .map(|e| vec![self.vertices[e.vertices[0]], self.vertices[e.vertices[1]]])
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        self.aabb
+    }
+}
+
+/// Visitor for finding the closest triangle of a mesh hit by a ray.
+struct MeshRayVisitor<'a> {
+    mesh: &'a Mesh,
+    ray: &'a Ray<f64>,
+
+    /// time of impact of the closest hit so far, or the maximum time
+    /// of impact if nothing has been hit
+    best_toi: f64,
+    hit: bool,
+}
+
+impl<'a> Visitor<usize, AABB<f64>> for MeshRayVisitor<'a> {
+    fn visit(&mut self, bv: &AABB<f64>, data: Option<&usize>) -> VisitStatus {
+        if bv
+            .toi_with_ray(&Isometry::identity(), self.ray, self.best_toi, true)
+            .is_none()
+        {
+            return VisitStatus::Stop;
+        }
+
+        if let Some(i) = data {
+            if let Some(toi) = self.mesh.occluding_triangle(*i).toi_with_ray(
+                &Isometry::identity(),
+                self.ray,
+                self.best_toi,
+                true,
+            ) {
+                self.best_toi = toi;
+                self.hit = true;
+            }
+        }
+        VisitStatus::Continue
+    }
+}
+
+/// Visitor for counting the triangles of a mesh crossed by a ray.
+struct MeshCrossingVisitor<'a> {
+    mesh: &'a Mesh,
+    ray: &'a Ray<f64>,
+    crossings: usize,
+}
+
+impl<'a> Visitor<usize, AABB<f64>> for MeshCrossingVisitor<'a> {
+    fn visit(&mut self, bv: &AABB<f64>, data: Option<&usize>) -> VisitStatus {
+        if !bv.intersects_ray(&Isometry::identity(), self.ray, f64::MAX) {
+            return VisitStatus::Stop;
+        }
+        if let Some(i) = data {
+            if self
+                .mesh
+                .triangle(*i)
+                .intersects_ray(&Isometry::identity(), self.ray, f64::MAX)
+            {
+                self.crossings += 1;
+            }
+        }
+        VisitStatus::Continue
+    }
+}
+
+/// Visitor for finding whether any triangle of a mesh lies within
+/// `radius` of a point.
+struct MeshPointVisitor<'a> {
+    mesh: &'a Mesh,
+    point: &'a Point3<f64>,
+    radius: f64,
+    near: bool,
+}
+
+impl<'a> Visitor<usize, AABB<f64>> for MeshPointVisitor<'a> {
+    fn visit(&mut self, bv: &AABB<f64>, data: Option<&usize>) -> VisitStatus {
+        if self.near || !bv.loosened(self.radius).contains_local_point(self.point) {
+            return VisitStatus::Stop;
+        }
+        if let Some(i) = data {
+            let distance =
+                self.mesh
+                    .triangle(*i)
+                    .distance_to_point(&Isometry::identity(), self.point, true);
+            if distance <= self.radius {
+                self.near = true;
+                return VisitStatus::ExitEarly;
+            }
+        }
+        VisitStatus::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::SceneBuilder;
+
+    /// Return a unit cube centered at the origin.
+    fn cube() -> Mesh {
+        let obj = "
+v -0.5 -0.5 -0.5
+v 0.5 -0.5 -0.5
+v 0.5 0.5 -0.5
+v -0.5 0.5 -0.5
+v -0.5 -0.5 0.5
+v 0.5 -0.5 0.5
+v 0.5 0.5 0.5
+v -0.5 0.5 0.5
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 2 3 7 6
+f 3 4 8 7
+f 4 1 5 8
+";
+        Mesh::parse_obj(obj.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn mesh_intersection() {
+        let mesh = cube();
+        assert_eq!(mesh.triangles().len(), 12);
+        // 12 box edges, plus one diagonal per face
+        assert_eq!(mesh.paths(&Camera::new()).len(), 18);
+
+        let ray = Ray::new(Point3::new(0.1, 0.2, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        // The occluding surface lies just inside the cube.
+        let toi = mesh.intersect(&ray, 10.0).unwrap();
+        assert!(toi > 4.5 && toi < 4.51);
+        assert!(mesh.intersect(&ray, 4.0).is_none());
+
+        let ray = Ray::new(Point3::new(0.6, 0.2, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(mesh.intersect(&ray, 10.0).is_none());
+
+        assert!(mesh.contains(&Point3::new(0.1, 0.2, 0.3), 0.0));
+        assert!(!mesh.contains(&Point3::new(0.6, 0.2, 0.3), 0.0));
+        assert!(mesh.contains(&Point3::new(0.6, 0.2, 0.3), 0.2));
+        assert!(!mesh.contains(&Point3::new(0.45, 0.2, 0.3), -0.1));
+    }
+
+    #[test]
+    fn wireframe_hides_back_edges() {
+        // Towards a corner, and nearly straight down onto the top face,
+        // seeing the sides at a grazing angle.
+        for eye in &[Point3::new(2.0, 1.5, 3.0), Point3::new(0.3, 3.0, 0.4)] {
+            let camera = Camera::new()
+                .look_at(eye, &Point3::origin(), &Vector3::y())
+                .perspective(1.0, 1.0, 0.5, 10.0);
+            let mesh = cube();
+
+            // Only the edges of faces towards the camera are visible.
+            let front: Vec<_> = mesh
+                .edges
+                .iter()
+                .filter(|e| {
+                    e.faces.iter().any(|f| {
+                        let v = mesh.vertices[mesh.triangles[*f][0]];
+                        mesh.normals[*f].dot(&camera.view_direction(&v)) > 0.0
+                    })
+                })
+                .map(|e| {
+                    let [a, b] = e.vertices;
+                    (
+                        camera.project(&mesh.vertices[a]),
+                        camera.project(&mesh.vertices[b]),
+                    )
+                })
+                .collect();
+            let on_front = |p: &Point2<f64>| {
+                front.iter().any(|(a, b)| {
+                    let d = b - a;
+                    let s = ((p - a).dot(&d) / d.norm_squared()).clamp(0.0, 1.0);
+                    (a + d * s - p).norm() < 1e-6
+                })
+            };
+
+            let paths = SceneBuilder::new().add(mesh).build().render(&camera);
+            assert_eq!(paths.len(), front.len());
+            for p in &paths {
+                assert!(p
+                    .points
+                    .windows(2)
+                    .all(|w| on_front(&na::center(&w[0], &w[1]))));
+            }
+        }
+    }
+
+    #[test]
+    fn cube_silhouette() {
+        let mesh = cube().edge_mode(EdgeMode::Silhouette);
+
+        // Looking straight at a face, the silhouette is its outline.
+        let camera = Camera::new().ortho(2.0, 2.0, 1.0, 10.0).look_at(
+            &Point3::new(0.0, 0.0, 5.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+        let paths = mesh.paths(&camera);
+        assert_eq!(paths.len(), 4);
+        for p in paths {
+            assert!(p.iter().all(|v| v.z == 0.5));
+        }
+
+        // From a corner, it is a hexagon.
+        let camera = camera.look_at(
+            &Point3::new(3.0, 3.0, 3.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+        assert_eq!(mesh.paths(&camera).len(), 6);
+    }
+
+    #[test]
+    fn cube_creases() {
+        // The face diagonals are flat, leaving only the box edges.
+        let camera = Camera::new();
+        let mesh = cube().edge_mode(EdgeMode::Crease(0.1));
+        assert_eq!(mesh.paths(&camera).len(), 12);
+        let mesh = mesh.edge_mode(EdgeMode::Crease(std::f64::consts::FRAC_PI_2));
+        assert_eq!(mesh.paths(&camera).len(), 0);
+
+        // A lone triangle is all boundary.
+        let triangle = Mesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2]],
+        )
+        .edge_mode(EdgeMode::Crease(1.0));
+        assert_eq!(triangle.paths(&camera).len(), 3);
+    }
+
+    #[test]
+    fn concave_edges_not_self_occluded() {
+        // An L-shaped prism, with a concave edge from (1, 1, 0) to
+        // (1, 1, 1).
+        let obj = "
+v 0 0 0
+v 2 0 0
+v 2 1 0
+v 1 1 0
+v 1 2 0
+v 0 2 0
+v 0 0 1
+v 2 0 1
+v 2 1 1
+v 1 1 1
+v 1 2 1
+v 0 2 1
+f 7 8 9 10 11 12
+f 1 6 5 4 3 2
+f 1 2 8 7
+f 2 3 9 8
+f 3 4 10 9
+f 4 5 11 10
+f 5 6 12 11
+f 6 1 7 12
+";
+        let mesh = Mesh::parse_obj(obj.as_bytes()).unwrap();
+
+        // Rays into the notch reach points along the concave edge, at
+        // its ends and at the convex corners before anything else.
+        for target in &[
+            Point3::new(1.0, 1.0, 0.5),
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(2.0, 1.0, 1.0),
+        ] {
+            let origin = Point3::new(3.0, 2.5, 2.0);
+            let ray = Ray::new(origin, (target - origin).normalize());
+            let distance = (target - origin).norm();
+            assert!(mesh.intersect(&ray, 10.0).unwrap() > distance);
+        }
+        assert!(mesh.contains(&Point3::new(0.5, 1.5, 0.5), 0.0));
+        assert!(!mesh.contains(&Point3::new(1.5, 1.5, 0.5), 0.0));
+    }
+
+    #[test]
+    fn welding_across_cells() {
+        // Two triangles sharing an edge, whose copies of the shared
+        // vertices straddle multiples of the tolerance.
+        let vertices = vec![
+            Point3::new(0.000_99, 0.0, 0.0),
+            Point3::new(0.0, 1.000_49, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(0.001_01, 0.0, 0.0),
+            Point3::new(0.0, 1.000_51, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        ];
+        let mesh = Mesh::new(vertices, vec![[0, 1, 2], [3, 5, 4]]).welded(1e-3);
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.paths(&Camera::new()).len(), 5);
+
+        // Vertices farther apart than the tolerance stay separate,
+        // even within the same cell.
+        let vertices = vec![Point3::new(0.0, 0.0, 0.0), Point3::new(0.0015, 0.0015, 0.0)];
+        let mesh = Mesh::new(vertices, vec![]).welded(2e-3);
+        assert_eq!(mesh.vertices().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "weld tolerance must be positive")]
+    fn zero_weld_tolerance() {
+        Mesh::new(vec![Point3::origin()], vec![]).welded(0.0);
+    }
+}
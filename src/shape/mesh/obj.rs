@@ -0,0 +1,74 @@
+//! Loading meshes from Wavefront OBJ files.
+use super::Mesh;
+use crate::common::*;
+use crate::error::LoadError;
+use std::io::BufRead;
+
+impl Mesh {
+    /// Load a mesh from a Wavefront OBJ file.
+    ///
+    /// Only vertex positions and faces are used; polygonal faces are
+    /// triangulated as fans.
+    pub fn load_obj<P: AsRef<std::path::Path>>(path: P) -> Result<Mesh, LoadError> {
+        let file = std::fs::File::open(path)?;
+        Mesh::parse_obj(std::io::BufReader::new(file))
+    }
+
+    /// Parse a mesh from Wavefront OBJ data.
+    pub fn parse_obj<R: BufRead>(reader: R) -> Result<Mesh, LoadError> {
+        let mut vertices = vec![];
+        let mut triangles = vec![];
+
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_no = line_no + 1;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coords = tokens
+                        .take(3)
+                        .map(|t| t.parse::<f64>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| LoadError::parse(line_no, format!("invalid vertex: {}", e)))?;
+                    if coords.len() != 3 {
+                        return Err(LoadError::parse(line_no, "vertex needs 3 coordinates"));
+                    }
+                    vertices.push(Point3::new(coords[0], coords[1], coords[2]));
+                }
+                Some("f") => {
+                    let indices = tokens
+                        .map(|t| parse_index(t, vertices.len()))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| LoadError::parse(line_no, e))?;
+                    if indices.len() < 3 {
+                        return Err(LoadError::parse(line_no, "face needs at least 3 vertices"));
+                    }
+                    for i in 1..indices.len() - 1 {
+                        triangles.push([indices[0], indices[i], indices[i + 1]]);
+                    }
+                }
+                // Normals, texture coordinates, groups, materials,
+                // etc. have no bearing on the rendered lines.
+                _ => {}
+            }
+        }
+
+        Ok(Mesh::new(vertices, triangles))
+    }
+}
+
+/// Parse a face vertex reference (`v`, `v/vt`, `v//vn` or `v/vt/vn`)
+/// into a zero-based vertex index, given `n` vertices so far.
+fn parse_index(token: &str, n: usize) -> Result<usize, String> {
+    let v = token.split('/').next().unwrap_or("");
+    let i: isize = v
+        .parse()
+        .map_err(|_| format!("invalid vertex index '{}'", v))?;
+    // OBJ indices are one-based, and negative indices count back from
+    // the most recent vertex.
+    let index = if i > 0 { i - 1 } else { n as isize + i };
+    if i == 0 || index < 0 || index as usize >= n {
+        return Err(format!("vertex index {} out of range", i));
+    }
+    Ok(index as usize)
+}
@@ -0,0 +1,190 @@
+//! Loading meshes from ASCII and binary STL files.
+use super::Mesh;
+use crate::common::*;
+use crate::error::LoadError;
+use std::convert::TryInto;
+use std::io::Read;
+
+/// Distance under which vertices of an STL file are welded together.
+///
+/// STL stores every triangle separately, so without welding no two
+/// triangles would share an edge.
+const STL_WELD_TOLERANCE: f64 = 1e-6;
+
+/// Size of the header of a binary STL file: an 80 byte comment,
+/// followed by a 32-bit triangle count.
+const BINARY_HEADER_LEN: usize = 84;
+
+/// Size of each triangle in a binary STL file: a normal, three
+/// vertices, and a 16-bit attribute count.
+const BINARY_TRIANGLE_LEN: usize = 50;
+
+impl Mesh {
+    /// Load a mesh from an ASCII or binary STL file.
+    ///
+    /// Duplicate vertices are welded, so edges shared between
+    /// triangles are only drawn once.
+    pub fn load_stl<P: AsRef<std::path::Path>>(path: P) -> Result<Mesh, LoadError> {
+        let file = std::fs::File::open(path)?;
+        Mesh::parse_stl(std::io::BufReader::new(file))
+    }
+
+    /// Parse a mesh from ASCII or binary STL data.
+    pub fn parse_stl<R: Read>(mut reader: R) -> Result<Mesh, LoadError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        // Binary files may also begin with "solid", so prefer the
+        // binary interpretation whenever the length is consistent
+        // with it.
+        let soup = if binary_len(&data) == Some(data.len()) || !data.starts_with(b"solid") {
+            parse_binary(&data)?
+        } else {
+            parse_ascii(&data)?
+        };
+
+        let triangles = (0..soup.len() / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect();
+        Ok(Mesh::new(soup, triangles).welded(STL_WELD_TOLERANCE))
+    }
+}
+
+/// Return the expected length of binary STL data, based on the
+/// triangle count in its header.
+fn binary_len(data: &[u8]) -> Option<usize> {
+    let count = data.get(80..BINARY_HEADER_LEN)?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    Some(BINARY_HEADER_LEN + count * BINARY_TRIANGLE_LEN)
+}
+
+/// Parse binary STL data into a list of vertices, three per triangle.
+fn parse_binary(data: &[u8]) -> Result<Vec<Point3<f64>>, LoadError> {
+    let expected = binary_len(data).unwrap_or(BINARY_HEADER_LEN);
+    if data.len() < expected {
+        return Err(LoadError::Truncated {
+            expected,
+            found: data.len(),
+        });
+    }
+
+    let read_f32 =
+        |offset: usize| f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as f64;
+    let mut vertices = vec![];
+    for triangle in (BINARY_HEADER_LEN..expected).step_by(BINARY_TRIANGLE_LEN) {
+        // skip the normal, which is recomputed from the vertices
+        for v in 1..4 {
+            let offset = triangle + v * 12;
+            vertices.push(Point3::new(
+                read_f32(offset),
+                read_f32(offset + 4),
+                read_f32(offset + 8),
+            ));
+        }
+    }
+    Ok(vertices)
+}
+
+/// Parse ASCII STL data into a list of vertices, three per triangle.
+fn parse_ascii(data: &[u8]) -> Result<Vec<Point3<f64>>, LoadError> {
+    let text = std::str::from_utf8(data).map_err(|e| LoadError::parse(1, e.to_string()))?;
+    let mut vertices = vec![];
+    let mut loop_len = None;
+    for (line_no, line) in text.lines().enumerate() {
+        let line_no = line_no + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("outer") => loop_len = Some(0),
+            Some("vertex") => {
+                let coords = tokens
+                    .map(|t| t.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| LoadError::parse(line_no, format!("invalid vertex: {}", e)))?;
+                if coords.len() != 3 {
+                    return Err(LoadError::parse(line_no, "vertex needs 3 coordinates"));
+                }
+                match loop_len.as_mut() {
+                    Some(n) => *n += 1,
+                    None => return Err(LoadError::parse(line_no, "vertex outside of a loop")),
+                }
+                vertices.push(Point3::new(coords[0], coords[1], coords[2]));
+            }
+            Some("endloop") => {
+                let n = loop_len.take();
+                if n != Some(3) {
+                    return Err(LoadError::parse(line_no, "facet needs exactly 3 vertices"));
+                }
+            }
+            // solid, facet, endfacet and endsolid carry nothing that
+            // the rendered lines need.
+            _ => {}
+        }
+    }
+    if loop_len.is_some() {
+        return Err(LoadError::parse(text.lines().count(), "unterminated loop"));
+    }
+    Ok(vertices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Shape;
+    use crate::Camera;
+
+    const ASCII_QUAD: &str = "solid quad
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 1 1 0
+  endloop
+endfacet
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 1 0
+    vertex 0 1 0
+  endloop
+endfacet
+endsolid quad
+";
+
+    #[test]
+    fn ascii_stl_is_welded() {
+        let mesh = Mesh::parse_stl(ASCII_QUAD.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.triangles().len(), 2);
+        // 4 sides and a shared diagonal
+        assert_eq!(mesh.paths(&Camera::new()).len(), 5);
+    }
+
+    #[test]
+    fn binary_stl() {
+        let quad = Mesh::parse_stl(ASCII_QUAD.as_bytes()).unwrap();
+
+        // header deliberately begins with "solid"
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&(quad.triangles().len() as u32).to_le_bytes());
+        for t in quad.triangles() {
+            data.extend_from_slice(&[0; 12]);
+            for i in t {
+                for c in quad.vertices()[*i].iter() {
+                    data.extend_from_slice(&(*c as f32).to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&[0; 2]);
+        }
+
+        let mesh = Mesh::parse_stl(&data[..]).unwrap();
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.triangles().len(), 2);
+
+        data[..5].copy_from_slice(b"quads");
+        match Mesh::parse_stl(&data[..data.len() - 1]) {
+            Err(LoadError::Truncated { .. }) => {}
+            _ => panic!("expected an error for truncated binary data"),
+        }
+    }
+}
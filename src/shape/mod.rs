@@ -1,5 +1,12 @@
 mod box_outline;
+mod cone;
+mod csg;
+mod cylinder;
+mod function_surface;
+mod heightmap;
+mod mesh;
 mod sphere;
+mod transformed;
 
 use super::common::*;
 use crate::camera::Camera;
@@ -16,8 +23,22 @@ pub type Paths = Vec<Path>;
 /// lie 'on' the shape within some tolerance.
 pub trait Shape: Send + Sync {
     /// Ray-intersection
+    ///
+    /// Return the time of impact of the first hit of the shape along
+    /// the ray, if it happens before `max_toi`. Solid shapes, such as
+    /// `Sphere`, hit a ray starting inside them at once, so that they
+    /// hide everything behind a near plane cutting through them.
     fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64>;
 
+    /// Ray-intersection with the surface of the shape
+    ///
+    /// Like `intersect`, but a ray starting inside the shape hits the
+    /// surface on its way out. Used by CSG shapes, which follow rays
+    /// from one operand surface to the next.
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.intersect(ray, max_toi)
+    }
+
     /// Return the set of paths that lie on the shape to render.
     fn paths(&self, camera: &Camera) -> Paths;
 
@@ -27,6 +48,66 @@ pub trait Shape: Send + Sync {
     fn name(&self) -> String {
         "Shape".to_string()
     }
+
+    /// Return true if the point lies inside the shape, grown by `eps`
+    /// in every direction (or shrunk, for negative `eps`).
+    ///
+    /// Shapes without an interior contain nothing.
+    fn contains(&self, _point: &Point3<f64>, _eps: f64) -> bool {
+        false
+    }
+}
+
+impl<S: Shape + ?Sized> Shape for Box<S> {
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        (**self).intersect(ray, max_toi)
+    }
+
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        (**self).surface_intersect(ray, max_toi)
+    }
+
+    fn paths(&self, camera: &Camera) -> Paths {
+        (**self).paths(camera)
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        (**self).bounding_box()
+    }
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        (**self).contains(point, eps)
+    }
+}
+
+impl<S: Shape + ?Sized> Shape for std::sync::Arc<S> {
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        (**self).intersect(ray, max_toi)
+    }
+
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        (**self).surface_intersect(ray, max_toi)
+    }
+
+    fn paths(&self, camera: &Camera) -> Paths {
+        (**self).paths(camera)
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        (**self).bounding_box()
+    }
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        (**self).contains(point, eps)
+    }
 }
 
 pub trait Textureable: Shape {
@@ -34,5 +115,82 @@ pub trait Textureable: Shape {
     fn uv_to_point(&self, uv: &Point2<f64>) -> Option<Point3<f64>>;
 }
 
+/// Factor by which shapes shrink the surface used for their
+/// occlusion.
+///
+/// Paths drawn on a shape lie on its true surface, so an occluder in
+/// exactly the same place would hide them wherever rounding puts them
+/// just behind it. Shrinking the occluder slightly keeps each shape
+/// from occluding its own paths, while still hiding what lies behind
+/// it.
+pub(crate) const OCCLUSION_SHRINK: f64 = 0.99;
+
+/// Return the first of the times of impact `tois` between zero and
+/// `max_toi`, if any.
+pub(crate) fn nearest_hit(tois: impl IntoIterator<Item = f64>, max_toi: f64) -> Option<f64> {
+    tois.into_iter()
+        .filter(|t| *t >= 0.0 && *t <= max_toi)
+        .fold(None, |best: Option<f64>, t| {
+            Some(best.map_or(t, |b| b.min(t)))
+        })
+}
+
+/// Return angles around a full turn, roughly `spacing` radians apart.
+///
+/// The spacing is rounded to a whole number of angles, so that it is
+/// even all the way around.
+pub(crate) fn even_angles(spacing: f64) -> impl Iterator<Item = f64> {
+    let n = ((std::f64::consts::TAU / spacing).round() as usize).max(1);
+    (0..n).map(move |i| std::f64::consts::TAU / n as f64 * i as f64)
+}
+
+/// Return a closed path tracing a circle of `radius` around the local
+/// y-axis of `transform`, at height `y`.
+pub(crate) fn ring_path(transform: &Isometry3<f64>, y: f64, radius: f64) -> Path {
+    const N_SEGMENTS: usize = 100;
+    (0..=N_SEGMENTS)
+        .map(|i| {
+            let theta = std::f64::consts::TAU / (N_SEGMENTS as f64) * i as f64;
+            let (s, c) = theta.sin_cos();
+            transform * Point3::new(radius * s, y, radius * c)
+        })
+        .collect()
+}
+
+/// Check that `spacing`, between the lines or samples named by
+/// `what`, is positive and finite, so that there are finitely many.
+///
+/// Also used for tolerances and sizes that are divided by.
+pub(crate) fn assert_spacing(spacing: f64, what: &str) {
+    assert!(
+        spacing > 0.0 && spacing.is_finite(),
+        "{} must be positive and finite, not {}",
+        what,
+        spacing
+    );
+}
+
+/// Return the rotation taking the y-axis onto `axis`.
+pub(crate) fn rotation_from_y(axis: &Vector3<f64>) -> na::UnitQuaternion<f64> {
+    na::UnitQuaternion::rotation_between(&Vector3::y(), axis).unwrap_or_else(|| {
+        // `axis` points straight down the y-axis, so any half-turn
+        // perpendicular to it will do.
+        na::UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f64::consts::PI)
+    })
+}
+
+/// Return the bounding box of a disc with a (unit) `normal`.
+pub(crate) fn disc_aabb(center: &Point3<f64>, normal: &Vector3<f64>, radius: f64) -> AABB<f64> {
+    let half_extents = normal.map(|n| radius * (1.0 - n * n).max(0.0).sqrt());
+    AABB::from_half_extents(*center, half_extents)
+}
+
 pub use box_outline::BoxOutline;
+pub use cone::Cone;
+pub use csg::{Difference, Intersection, Union};
+pub use cylinder::Cylinder;
+pub use function_surface::{FunctionSurface, SurfaceStrokes};
+pub use heightmap::Heightmap;
+pub use mesh::{EdgeMode, Mesh};
 pub use sphere::Sphere;
+pub use transformed::TransformedShape;
@@ -1,13 +1,20 @@
 use crate::common::*;
-use crate::shape::{Camera, Path, Paths, Shape};
+use crate::shape::{
+    assert_spacing, even_angles, ring_path, Camera, Path, Paths, Shape, OCCLUSION_SHRINK,
+};
 use ncollide3d::query::RayCast;
 
-/// Sphere with lattitude and longitude lines oriented around the y-axis.
+/// Sphere with lattitude and longitude lines.
+///
+/// The lines are oriented around the local y-axis of the sphere's
+/// transform, which is the world y-axis unless the sphere has been
+/// given an explicit orientation.
 #[derive(Clone)]
 pub struct Sphere {
+    /// local-to-world transform, with the sphere centered at the
+    /// local origin
     transform: Isometry3<f64>,
 
-    pos: Point3<f64>,
     radius: f64,
     /// internal use of a ncollide3d Sphere object for ray-intersection
     shape: ncollide3d::shape::Ball<f64>,
@@ -17,38 +24,100 @@ pub struct Sphere {
 
     /// angle spancing for longitude lines.
     long_angle: Option<f64>,
+
+    /// angular distance from each pole at which longitude lines stop.
+    pole_gap: f64,
 }
 
 impl Sphere {
+    /// Create a sphere centered at `pos`, with lines spaced
+    /// `lat_angle` and `long_angle` radians apart, if given.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either angle is not positive and finite.
     pub fn new(
         pos: &Point3<f64>,
         radius: f64,
         lat_angle: Option<f64>,
         long_angle: Option<f64>,
     ) -> Sphere {
-        let ball = ncollide3d::shape::Ball::new(radius * 0.99);
         let transform = na::Isometry3::translation(pos.x, pos.y, pos.z);
+        Sphere::from_isometry(&transform, radius, lat_angle, long_angle)
+    }
+
+    /// Create a sphere centered at the translation of `transform`,
+    /// with latitude and longitude lines around its rotated y-axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either angle is not positive and finite.
+    pub fn from_isometry(
+        transform: &Isometry3<f64>,
+        radius: f64,
+        lat_angle: Option<f64>,
+        long_angle: Option<f64>,
+    ) -> Sphere {
+        if let Some(angle) = lat_angle {
+            assert_spacing(angle, "latitude angle");
+        }
+        if let Some(angle) = long_angle {
+            assert_spacing(angle, "longitude angle");
+        }
+        let ball = ncollide3d::shape::Ball::new(radius * OCCLUSION_SHRINK);
         Sphere {
-            transform,
-            pos: *pos,
+            transform: *transform,
             shape: ball,
             radius,
             lat_angle,
             long_angle,
+            pole_gap: 0.0,
         }
     }
 
+    /// Return a modified version of the sphere whose longitude lines
+    /// stop `angle` radians short of each pole.
+    ///
+    /// Useful for keeping converging meridians from piling up into a
+    /// blob when plotted.
+    pub fn pole_gap(self, angle: f64) -> Sphere {
+        Sphere {
+            pole_gap: angle.clamp(0.0, std::f64::consts::FRAC_PI_2),
+            ..self
+        }
+    }
+
+    /// Return a modified version of the sphere with its poles along
+    /// the y-axis rotated by `rotation`, keeping the same center.
+    pub fn orientation(self, rotation: &na::UnitQuaternion<f64>) -> Sphere {
+        let transform = Isometry3::from_parts(self.transform.translation, *rotation);
+        Sphere { transform, ..self }
+    }
+
+    /// Return the center of the sphere.
+    pub fn center(&self) -> Point3<f64> {
+        self.transform.translation.vector.into()
+    }
+
     /// Return a path trading a latitutde line at a sepcific latitude.
     fn latitude_path(&self, angle: f64) -> Path {
-        const N_SEGMENTS: usize = 100;
         let (s, c) = angle.sin_cos();
-        let radius_to_axis = c * self.radius;
-        let y = s * self.radius;
+        ring_path(&self.transform, s * self.radius, c * self.radius)
+    }
+
+    /// Return a path tracing a longitude line from pole to pole at a
+    /// specific longitude, stopping `pole_gap` short of either pole.
+    fn longitude_path(&self, angle: f64) -> Path {
+        const N_SEGMENTS: usize = 50;
+        let (s, c) = angle.sin_cos();
+        let max_lat = std::f64::consts::FRAC_PI_2 - self.pole_gap;
         (0..=N_SEGMENTS)
             .map(|i| {
-                let theta = std::f64::consts::TAU / (N_SEGMENTS as f64) * i as f64;
-                let (s, c) = theta.sin_cos();
-                self.pos + Vector3::new(radius_to_axis * s, y, radius_to_axis * c)
+                let lat = -max_lat + 2.0 * max_lat / (N_SEGMENTS as f64) * i as f64;
+                let (lat_s, lat_c) = lat.sin_cos();
+                let radius_to_axis = lat_c * self.radius;
+                self.transform
+                    * Point3::new(radius_to_axis * s, lat_s * self.radius, radius_to_axis * c)
             })
             .collect()
     }
@@ -56,28 +125,121 @@ impl Sphere {
 
 impl Shape for Sphere {
     fn name(&self) -> String {
-        format!("Sphere")
+        "Sphere".to_string()
     }
     fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
         self.shape.toi_with_ray(&self.transform, ray, max_toi, true)
     }
 
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.shape.toi_with_ray(&self.transform, ray, max_toi, false)
+    }
+
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        (point - self.center()).norm() <= self.radius + eps
+    }
+
     fn paths(&self, _camera: &Camera) -> Paths {
         let mut paths = vec![];
         if let Some(lat_angle) = self.lat_angle {
             paths.push(self.latitude_path(0.0));
-            let mut rising_angle = 0.0;
+            let mut rising_angle = lat_angle;
             while rising_angle < std::f64::consts::FRAC_PI_2 {
                 paths.push(self.latitude_path(rising_angle));
                 paths.push(self.latitude_path(-rising_angle));
                 rising_angle += lat_angle;
             }
         }
+        if let Some(long_angle) = self.long_angle {
+            if self.pole_gap < std::f64::consts::FRAC_PI_2 {
+                paths.extend(even_angles(long_angle).map(|theta| self.longitude_path(theta)));
+            }
+        }
 
         paths
     }
     fn bounding_box(&self) -> AABB<f64> {
         let half_extents = Vector3::new(self.radius, self.radius, self.radius);
-        AABB::from_half_extents(self.pos, half_extents)
+        AABB::from_half_extents(self.center(), half_extents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn rotated_paths_lie_on_sphere() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let tilt = na::UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.4);
+        let sphere = Sphere::new(&center, 2.0, Some(0.5), Some(0.5)).orientation(&tilt);
+        assert_eq!(sphere.center(), center);
+
+        let camera = Camera::new();
+        for path in sphere.paths(&camera) {
+            for p in path {
+                assert_relative_eq!((p - center).norm(), 2.0, epsilon = 1e-9);
+            }
+        }
+
+        // The north pole now lies along the tilted axis, and a ray
+        // aimed at it from outside hits just short of the surface.
+        let pole = center + tilt * Vector3::new(0.0, 2.0, 0.0);
+        let dir = tilt * Vector3::y();
+        let ray = Ray::new(pole + dir * 5.0, -dir);
+        let toi = sphere.intersect(&ray, 10.0).unwrap();
+        assert!(toi > 5.0 - 1e-9 && toi < 5.0 + 0.05);
+    }
+
+    #[test]
+    fn longitude_lines_stop_short_of_poles() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let gap = 0.2;
+        let sphere =
+            Sphere::new(&center, 2.0, None, Some(std::f64::consts::FRAC_PI_4)).pole_gap(gap);
+
+        let paths = sphere.paths(&Camera::new());
+        assert_eq!(paths.len(), 8);
+        let max_y = 2.0 * gap.cos();
+        for path in paths {
+            assert_relative_eq!(path[0].y - center.y, -max_y, epsilon = 1e-9);
+            assert_relative_eq!(path[path.len() - 1].y - center.y, max_y, epsilon = 1e-9);
+            for p in path {
+                assert_relative_eq!((p - center).norm(), 2.0, epsilon = 1e-9);
+                assert!((p.y - center.y).abs() <= max_y + 1e-9);
+            }
+        }
+
+        // Without a gap the lines start at the south pole, and a full gap
+        // leaves no lines at all.
+        let sphere = Sphere::new(&center, 2.0, None, Some(std::f64::consts::FRAC_PI_4));
+        for path in sphere.paths(&Camera::new()) {
+            let south = center - Vector3::new(0.0, 2.0, 0.0);
+            assert_relative_eq!((path[0] - south).norm(), 0.0, epsilon = 1e-9);
+        }
+        let sphere = sphere.pole_gap(std::f64::consts::FRAC_PI_2);
+        assert!(sphere.paths(&Camera::new()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "longitude angle must be positive")]
+    fn zero_longitude_angle() {
+        Sphere::new(&Point3::origin(), 1.0, None, Some(0.0));
+    }
+
+    #[test]
+    fn equator_drawn_once() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let sphere = Sphere::new(&center, 2.0, Some(0.5), None);
+
+        // The equator, then a pair of lines at 0.5, 1.0 and 1.5.
+        let paths = sphere.paths(&Camera::new());
+        assert_eq!(paths.len(), 7);
+        let equators = paths
+            .iter()
+            .filter(|path| path.iter().all(|p| (p.y - center.y).abs() < 1e-9))
+            .count();
+        assert_eq!(equators, 1);
     }
 }
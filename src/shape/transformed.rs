@@ -0,0 +1,167 @@
+//! Shapes placed with an arbitrary affine transform.
+use crate::common::*;
+use crate::shape::{Camera, Paths, Shape};
+
+/// Wrapper placing any shape in the world with an affine transform.
+///
+/// Rays are mapped into the local space of the wrapped shape, and its
+/// paths out into world space. This allows shapes without a transform
+/// of their own, such as `BoxOutline`, to be rotated, and any shape to
+/// be scaled or sheared.
+#[derive(Clone)]
+pub struct TransformedShape<S: Shape> {
+    shape: S,
+
+    /// local-to-world transform
+    transform: Matrix4<f64>,
+
+    /// world-to-local transform
+    inverse: Matrix4<f64>,
+}
+
+impl<S: Shape> TransformedShape<S> {
+    /// Place `shape` rigidly with `transform`.
+    pub fn new(shape: S, transform: &Isometry3<f64>) -> TransformedShape<S> {
+        TransformedShape {
+            shape,
+            transform: transform.to_homogeneous(),
+            inverse: transform.inverse().to_homogeneous(),
+        }
+    }
+
+    /// Place `shape` with a general affine `transform`.
+    ///
+    /// Return `None` if the transform is not affine or can't be
+    /// inverted.
+    pub fn from_matrix(shape: S, transform: Matrix4<f64>) -> Option<TransformedShape<S>> {
+        if transform.fixed_rows::<na::U1>(3) != Matrix4::identity().fixed_rows::<na::U1>(3) {
+            return None;
+        }
+        let inverse = transform.try_inverse()?;
+        Some(TransformedShape {
+            shape,
+            transform,
+            inverse,
+        })
+    }
+
+    /// Return the wrapped shape.
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    /// Return the local-to-world transform.
+    pub fn transform(&self) -> &Matrix4<f64> {
+        &self.transform
+    }
+
+    /// Return a ray mapped into the local space of the shape.
+    ///
+    /// The direction isn't renormalized, so times of impact along the
+    /// local ray are the same as along the world ray.
+    fn local_ray(&self, ray: &Ray<f64>) -> Ray<f64> {
+        Ray::new(
+            self.inverse.transform_point(&ray.origin),
+            self.inverse.transform_vector(&ray.dir),
+        )
+    }
+}
+
+impl<S: Shape> Shape for TransformedShape<S> {
+    fn name(&self) -> String {
+        format!("Transformed {}", self.shape.name())
+    }
+
+    fn intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.shape.intersect(&self.local_ray(ray), max_toi)
+    }
+
+    fn surface_intersect(&self, ray: &Ray<f64>, max_toi: f64) -> Option<f64> {
+        self.shape.surface_intersect(&self.local_ray(ray), max_toi)
+    }
+
+    /// `eps` is scaled by the average scale of the transform, which is
+    /// only exact for uniform scales.
+    fn contains(&self, point: &Point3<f64>, eps: f64) -> bool {
+        let scale = self
+            .inverse
+            .fixed_slice::<na::U3, na::U3>(0, 0)
+            .determinant()
+            .abs()
+            .cbrt();
+        self.shape
+            .contains(&self.inverse.transform_point(point), eps * scale)
+    }
+
+    fn paths(&self, camera: &Camera) -> Paths {
+        self.shape
+            .paths(&camera.in_local_space(&self.transform))
+            .into_iter()
+            .map(|path| {
+                path.iter()
+                    .map(|p| self.transform.transform_point(p))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB<f64> {
+        // Transform every corner of the local box, and bound those.
+        let bb = self.shape.bounding_box();
+        let corners: Vec<_> = (0..8)
+            .map(|i| {
+                let corner = Point3::new(
+                    if i & 1 == 0 { bb.mins.x } else { bb.maxs.x },
+                    if i & 2 == 0 { bb.mins.y } else { bb.maxs.y },
+                    if i & 4 == 0 { bb.mins.z } else { bb.maxs.z },
+                );
+                self.transform.transform_point(&corner)
+            })
+            .collect();
+        ncollide3d::bounding_volume::local_point_cloud_aabb(&corners)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::BoxOutline;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn rotated_box() {
+        let unit = BoxOutline::new(Point3::origin(), Vector3::new(0.5, 0.5, 0.5));
+        let rotation = Isometry3::new(
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::y() * std::f64::consts::FRAC_PI_4,
+        );
+        let shape = TransformedShape::new(unit, &rotation);
+
+        // The corner of the rotated box now points along the x-axis.
+        let ray = Ray::new(Point3::new(5.0, 1.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert_relative_eq!(
+            shape.intersect(&ray, 10.0).unwrap(),
+            5.0 - 0.5f64.sqrt(),
+            epsilon = 1e-9
+        );
+        let bb = shape.bounding_box();
+        assert_relative_eq!(bb.maxs.x, 0.5f64.sqrt(), epsilon = 1e-9);
+        assert_relative_eq!(bb.maxs.y, 1.5, epsilon = 1e-9);
+        assert!(shape.contains(&Point3::new(0.6, 1.0, 0.0), 0.0));
+
+        // Paths move with the box.
+        for p in shape.paths(&Camera::new()).iter().flatten() {
+            assert_relative_eq!(p.x.abs() + p.z.abs(), 0.5f64.sqrt(), epsilon = 1e-9);
+        }
+
+        // A non-uniform scale stretches rays along with the shape.
+        let scale = Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 1.0));
+        let stretched = TransformedShape::from_matrix(shape, scale).unwrap();
+        assert_relative_eq!(
+            stretched.intersect(&ray, 10.0).unwrap(),
+            5.0 - 2.0 * 0.5f64.sqrt(),
+            epsilon = 1e-9
+        );
+        assert!(TransformedShape::from_matrix(stretched, Matrix4::zeros()).is_none());
+    }
+}
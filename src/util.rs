@@ -31,6 +31,33 @@ pub fn box_plane_intersection(bb: &AABB<f64>, plane: &Vector4<f64>) -> BoxPlaneT
     }
 }
 
+/// Return the range of times `(t_min, t_max)` over which the ray lies
+/// within the bounding box, if it ever does.
+///
+/// The range may begin before zero, if the ray starts inside the box.
+pub fn ray_aabb_interval(bb: &AABB<f64>, ray: &Ray<f64>) -> Option<(f64, f64)> {
+    let mut t_min = f64::NEG_INFINITY;
+    let mut t_max = f64::INFINITY;
+    for i in 0..3 {
+        let (o, d) = (ray.origin[i], ray.dir[i]);
+        if d == 0.0 {
+            if o < bb.mins[i] || o > bb.maxs[i] {
+                return None;
+            }
+        } else {
+            let t0 = (bb.mins[i] - o) / d;
+            let t1 = (bb.maxs[i] - o) / d;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+    }
+    if t_min <= t_max {
+        Some((t_min, t_max))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;